@@ -0,0 +1,77 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use rw::*;
+use rw::con::{Consumer, ConsumeOperations, IntoVec, ScrambleConsumer};
+use rw::pro::{Producer, ProduceOperations, ScrambleProducer};
+use rw::base64::*;
+
+type Ops = (ConsumeOperations, ProduceOperations, ProduceOperations);
+
+fn config(url_safe: bool, padding: bool) -> Config {
+    Config {
+        alphabet: if url_safe { Alphabet::UrlSafe } else { Alphabet::Standard },
+        padding,
+    }
+}
+
+// Produces all items, and checks that the remaining hint was correct before each of them.
+fn produce_all<P: Producer>(p: &mut P) -> (Vec<P::Item>, P::Error) {
+    let mut items = Vec::new();
+    let mut hints = Vec::new();
+    let e = loop {
+        hints.push(p.remaining_hint());
+        match p.produce() {
+            Ok(item) => items.push(item),
+            Err(e) => break e,
+        }
+    };
+
+    for (i, (lower, upper)) in hints.into_iter().enumerate() {
+        let remaining = items.len() - i;
+        assert!(lower <= remaining);
+        assert!(upper.map_or(true, |upper| remaining <= upper));
+    }
+    (items, e)
+}
+
+// Encodes the bytes, decodes the encoding, and checks that the result equals the bytes.
+// Flushing ends the text, so the encoder itself is not scrambled, but its input and output are.
+fn round_trip(bytes: &[u8], config: Config, ops: Ops, cap: NonZeroUsize) {
+    let (c_ops, p_ops_in, p_ops_out) = ops;
+    let mut p = ScrambleProducer::new(pro::cursor(bytes), p_ops_in, cap);
+    let mut e = encoder(ScrambleConsumer::new(con::map_err(IntoVec::new(), |e| match e {}), c_ops, cap), config);
+    assert_eq!(bulk_consume_all(&mut p, &mut e), ());
+    assert_eq!(e.flush(), Ok(()));
+    let encoded = e.into_inner().into_inner().into_inner().into_vec();
+    let groups = (bytes.len() + 2) / 3;
+    if config.padding {
+        assert_eq!(encoded.len(), groups * 4);
+    } else {
+        assert_eq!(encoded.len(), (bytes.len() * 4 + 2) / 3);
+    }
+
+    let mut d = ScrambleProducer::new(decoder(pro::cursor(&encoded[..]), config), p_ops_out, cap);
+    let (decoded, e) = produce_all(&mut d);
+    assert_eq!(&decoded[..], bytes);
+    assert_eq!(e, DecodeError::Inner(()));
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Box<[u8]>, Box<[u8]>, bool, bool, Ops, NonZeroUsize)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((bytes, text, url_safe, padding, ops, cap)) => {
+            let cap = NonZeroUsize::new(min(cap.get(), 16)).unwrap();
+            round_trip(&bytes[..], config(url_safe, padding), ops, cap);
+
+            // Decoding arbitrary text must neither panic nor misreport its remaining hint.
+            produce_all(&mut decoder(pro::cursor(&text[..]), config(url_safe, padding)));
+        }
+        _ => {}
+    }
+});
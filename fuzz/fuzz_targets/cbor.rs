@@ -0,0 +1,201 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use rw::con::{BulkConsumer, ConsumeOperations, IntoVec, ScrambleConsumer};
+use rw::pro::{self, Producer, BulkProducer, ProduceOperations, ScrambleProducer};
+use rw::cbor::*;
+
+// A call to the encoder.
+#[derive(Debug, Arbitrary)]
+enum Item {
+    Uint(u64),
+    Int(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    IndefiniteBytes(Vec<Vec<u8>>),
+    IndefiniteText(Vec<String>),
+    Array(Option<u64>),
+    Map(Option<u64>),
+    End,
+    Tag(u64),
+    Bool(bool),
+    Null,
+    Undefined,
+    Simple(u8),
+    F32(f32),
+    F64(f64),
+}
+
+// An event, with the chunks of a string merged and floats compared by their bits.
+#[derive(Debug, PartialEq)]
+enum Parsed {
+    Event(Event),
+    Float(u64),
+    Chunks(Vec<u8>),
+}
+
+fn float(value: f64) -> Parsed {
+    Parsed::Float(if value.is_nan() { f64::NAN.to_bits() } else { value.to_bits() })
+}
+
+fn string(expected: &mut Vec<Parsed>, start: Event, contents: Vec<u8>, indefinite: bool) {
+    expected.push(Parsed::Event(start));
+    if !contents.is_empty() {
+        expected.push(Parsed::Chunks(contents));
+    }
+    if indefinite {
+        expected.push(Parsed::Event(Event::Break));
+    }
+}
+
+// Writes the item, and appends the events a parser produces for it.
+fn encode<C: BulkConsumer<Item = u8>>(e: &mut Encoder<C>, item: &Item, expected: &mut Vec<Parsed>) where
+    C::Error: core::fmt::Debug,
+{
+    match item {
+        Item::Uint(value) => {
+            e.uint(*value).unwrap();
+            expected.push(Parsed::Event(Event::Uint(*value)));
+        }
+        Item::Int(value) => {
+            e.int(*value).unwrap();
+            expected.push(Parsed::Event(if *value < 0 { Event::NegInt(!(*value as u64)) } else { Event::Uint(*value as u64) }));
+        }
+        Item::Bytes(bytes) => {
+            e.bytes(bytes).unwrap();
+            string(expected, Event::BytesStart(Some(bytes.len() as u64)), bytes.clone(), false);
+        }
+        Item::Text(text) => {
+            e.text(text).unwrap();
+            string(expected, Event::TextStart(Some(text.len() as u64)), text.as_bytes().to_vec(), false);
+        }
+        Item::IndefiniteBytes(segments) => {
+            e.bytes_start(None).unwrap();
+            for segment in segments {
+                e.bytes(segment).unwrap();
+            }
+            e.end().unwrap();
+            string(expected, Event::BytesStart(None), segments.concat(), true);
+        }
+        Item::IndefiniteText(segments) => {
+            e.text_start(None).unwrap();
+            for segment in segments {
+                e.text(segment).unwrap();
+            }
+            e.end().unwrap();
+            string(expected, Event::TextStart(None), segments.concat().into_bytes(), true);
+        }
+        Item::Array(len) => {
+            e.array_start(*len).unwrap();
+            expected.push(Parsed::Event(Event::ArrayStart(*len)));
+        }
+        Item::Map(len) => {
+            e.map_start(*len).unwrap();
+            expected.push(Parsed::Event(Event::MapStart(*len)));
+        }
+        Item::End => {
+            e.end().unwrap();
+            expected.push(Parsed::Event(Event::Break));
+        }
+        Item::Tag(tag) => {
+            e.tag(*tag).unwrap();
+            expected.push(Parsed::Event(Event::Tag(*tag)));
+        }
+        Item::Bool(value) => {
+            e.bool(*value).unwrap();
+            expected.push(Parsed::Event(Event::Bool(*value)));
+        }
+        Item::Null => {
+            e.null().unwrap();
+            expected.push(Parsed::Event(Event::Null));
+        }
+        Item::Undefined => {
+            e.undefined().unwrap();
+            expected.push(Parsed::Event(Event::Undefined));
+        }
+        // These are not well-formed.
+        Item::Simple(24..=31) => {}
+        Item::Simple(value) => {
+            e.simple(*value).unwrap();
+            expected.push(Parsed::Event(match value {
+                20 => Event::Bool(false),
+                21 => Event::Bool(true),
+                22 => Event::Null,
+                23 => Event::Undefined,
+                _ => Event::Simple(*value),
+            }));
+        }
+        Item::F32(value) => {
+            e.f32(*value).unwrap();
+            expected.push(float(*value as f64));
+        }
+        Item::F64(value) => {
+            e.f64(*value).unwrap();
+            expected.push(float(*value));
+        }
+    }
+}
+
+// Parses all events, merging the chunks of strings. Returns the events and the final error.
+fn parse<P: BulkProducer<Item = u8>>(p: &mut Parser<P, Vec<u8>>) -> (Vec<Parsed>, ParseError<P::Error>) {
+    let mut parsed = Vec::new();
+    let mut hints = Vec::new();
+    let e = loop {
+        hints.push(p.remaining_hint());
+        match p.produce() {
+            Ok(Event::Bytes(len)) | Ok(Event::Text(len)) => {
+                assert_eq!(p.chunk().len(), len);
+                assert!(len > 0);
+                match parsed.last_mut() {
+                    Some(Parsed::Chunks(chunks)) => chunks.extend_from_slice(p.chunk()),
+                    _ => parsed.push(Parsed::Chunks(p.chunk().to_vec())),
+                }
+            }
+            Ok(Event::Float(value)) => parsed.push(float(value)),
+            Ok(event) => parsed.push(Parsed::Event(event)),
+            Err(e) => break e,
+        }
+    };
+
+    // Every event takes at least one byte of the input.
+    for (i, (_, upper)) in hints.iter().enumerate() {
+        assert!(upper.map_or(true, |upper| hints.len() - 1 - i <= upper));
+    }
+    (parsed, e)
+}
+
+// Encodes the items, parses the encoding, and checks that the events match the items.
+fn round_trip(items: &[Item], c_ops: ConsumeOperations, p_ops: ProduceOperations, cap: NonZeroUsize, buffer_len: NonZeroUsize) {
+    let mut e = encoder(ScrambleConsumer::new(IntoVec::new(), c_ops, cap));
+    let mut expected = Vec::new();
+    for item in items {
+        encode(&mut e, item, &mut expected);
+    }
+    assert_eq!(e.flush(), Ok(()));
+    let encoded = e.into_inner().into_inner().into_vec();
+
+    let mut p = parser(ScrambleProducer::new(pro::cursor(&encoded[..]), p_ops, cap), vec![0; buffer_len.get()]);
+    let (parsed, e) = parse(&mut p);
+    assert_eq!(parsed, expected);
+    assert_eq!(e, ParseError::Inner(()));
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Vec<Item>, Box<[u8]>, ConsumeOperations, ProduceOperations, NonZeroUsize, NonZeroUsize)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((items, bytes, c_ops, p_ops, cap, buffer_len)) => {
+            let cap = NonZeroUsize::new(min(cap.get(), 2048)).unwrap();
+            let buffer_len = NonZeroUsize::new(min(buffer_len.get(), 64)).unwrap();
+            round_trip(&items[..], c_ops, p_ops, cap, buffer_len);
+
+            // Parsing arbitrary bytes must neither panic nor misreport the remaining hint.
+            parse(&mut parser(pro::cursor(&bytes[..]), vec![0; buffer_len.get()]));
+        }
+        _ => {}
+    }
+});
@@ -0,0 +1,61 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use rw::con::{Consumer, ConsumeOperations, IntoVec, ScrambleConsumer};
+use rw::pro::{self, Producer, ProduceOperations, ScrambleProducer};
+use rw::cobs::*;
+
+// Encodes the frames, decodes the encodings, and checks that the results equal the frames.
+fn round_trip(frames: &[Vec<u8>], c_ops: ConsumeOperations, p_ops: ProduceOperations, cap: NonZeroUsize) {
+    let mut e = encoder(ScrambleConsumer::new(IntoVec::new(), c_ops, cap));
+    for frame in frames {
+        assert_eq!(e.consume(frame), Ok(()));
+    }
+    assert_eq!(e.flush(), Ok(()));
+    let encoded = e.into_inner().into_inner().into_vec();
+    assert_eq!(encoded.iter().filter(|byte| **byte == 0).count(), frames.len());
+
+    let max_len = frames.iter().map(|frame| frame.len()).max().unwrap_or(0);
+    let mut d = decoder(ScrambleProducer::new(pro::cursor(&encoded[..]), p_ops, cap), vec![0; max_len]);
+    for (i, frame) in frames.iter().enumerate() {
+        assert!(d.remaining_hint().1.map_or(true, |upper| frames.len() - i <= upper));
+        assert_eq!(d.produce(), Ok(frame.len()));
+        assert_eq!(d.frame(), &frame[..]);
+    }
+    assert_eq!(d.produce(), Err(DecodeError::Inner(())));
+}
+
+// Decodes arbitrary bytes, and checks that the decoder neither panics nor misreports its
+// remaining hint, and that it can continue after errors within frames.
+fn decode_arbitrary(bytes: &[u8], buffer_len: usize) {
+    let mut d = decoder(pro::cursor(bytes), vec![0; buffer_len]);
+    let mut hints = Vec::new();
+    loop {
+        hints.push(d.remaining_hint());
+        match d.produce() {
+            Ok(len) => assert!(len <= buffer_len),
+            Err(DecodeError::Corrupt) | Err(DecodeError::TooLong) => {}
+            Err(DecodeError::Inner(())) | Err(DecodeError::Incomplete) => break,
+        }
+    }
+
+    // Every frame takes at least its terminating zero.
+    assert!(hints[0].1.map_or(true, |upper| hints.len() - 1 <= upper));
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Vec<Vec<u8>>, Box<[u8]>, ConsumeOperations, ProduceOperations, NonZeroUsize, u8)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((frames, bytes, c_ops, p_ops, cap, buffer_len)) => {
+            let cap = NonZeroUsize::new(min(cap.get(), 2048)).unwrap();
+            round_trip(&frames[..], c_ops, p_ops, cap);
+            decode_arbitrary(&bytes[..], buffer_len as usize);
+        }
+        _ => {}
+    }
+});
@@ -0,0 +1,81 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use rw::con::{Consumer, ConsumeOperations, IntoVec, ScrambleConsumer};
+use rw::pro::{self, Producer, ProduceOperations, ScrambleProducer};
+use rw::csv::*;
+
+// Produces all items, and checks that the remaining hint was correct before each of them.
+fn produce_all<P: Producer>(p: &mut P) -> (Vec<P::Item>, P::Error) {
+    let mut items = Vec::new();
+    let mut hints = Vec::new();
+    let e = loop {
+        hints.push(p.remaining_hint());
+        match p.produce() {
+            Ok(item) => items.push(item),
+            Err(e) => break e,
+        }
+    };
+
+    for (i, (lower, upper)) in hints.into_iter().enumerate() {
+        let remaining = items.len() - i;
+        assert!(lower <= remaining);
+        assert!(upper.map_or(true, |upper| remaining <= upper));
+    }
+    (items, e)
+}
+
+// Writes the records, reads the text, and checks that the result equals the records. A record
+// without fields is read as a record with a single empty field.
+fn round_trip(written_records: &[Vec<Vec<u8>>], config: Config, c_ops: ConsumeOperations, p_ops: ProduceOperations, cap: NonZeroUsize, buffer_len: NonZeroUsize) {
+    let mut w = writer(ScrambleConsumer::new(IntoVec::new(), c_ops, cap), config);
+    for record in written_records {
+        assert_eq!(w.consume(record), Ok(()));
+    }
+    assert_eq!(w.flush(), Ok(()));
+    let written = w.into_inner().into_inner().into_vec();
+
+    let expected: Vec<Vec<Vec<u8>>> = written_records.iter()
+        .map(|record| if record.is_empty() { vec![Vec::new()] } else { record.clone() })
+        .collect();
+    let r = reader(ScrambleProducer::new(pro::cursor(&written[..]), p_ops, cap), vec![0; buffer_len.get()], config);
+    let (read, e) = produce_all(&mut records(r));
+    assert_eq!(read, expected);
+    assert_eq!(e, ReadError::Inner(()));
+}
+
+// Reads arbitrary bytes, and checks that the reader neither panics nor misreports its remaining
+// hint, and that its chunks fit into the buffer.
+fn read_arbitrary(bytes: &[u8], config: Config, buffer_len: NonZeroUsize) {
+    let mut r = reader(pro::cursor(bytes), vec![0; buffer_len.get()], config);
+    let (tokens, _) = produce_all(&mut r);
+    for token in tokens {
+        if let CsvToken::Chunk(len) = token {
+            assert!(0 < len && len <= buffer_len.get());
+        }
+    }
+    produce_all(&mut records(reader(pro::cursor(bytes), vec![0; buffer_len.get()], config)));
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Vec<Vec<Vec<u8>>>, Box<[u8]>, u8, u8, bool, ConsumeOperations, ProduceOperations, NonZeroUsize, NonZeroUsize)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((written_records, bytes, delimiter, quote, crlf, c_ops, p_ops, cap, buffer_len)) => {
+            // The delimiter and the quote must differ from each other and from line breaks.
+            if delimiter == quote || [delimiter, quote].iter().any(|byte| *byte == b'\r' || *byte == b'\n') {
+                return;
+            }
+            let config = Config { delimiter, quote, crlf };
+            let cap = NonZeroUsize::new(min(cap.get(), 2048)).unwrap();
+            let buffer_len = NonZeroUsize::new(min(buffer_len.get(), 64)).unwrap();
+            round_trip(&written_records[..], config, c_ops, p_ops, cap, buffer_len);
+            read_arbitrary(&bytes[..], config, buffer_len);
+        }
+        _ => {}
+    }
+});
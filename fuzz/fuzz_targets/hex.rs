@@ -0,0 +1,88 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use rw::*;
+use rw::con::{Consumer, ConsumeOperations, IntoVec, ScrambleConsumer};
+use rw::pro::{Producer, ProduceOperations, ScrambleProducer};
+use rw::hex::*;
+
+type Ops = (ConsumeOperations, ProduceOperations, ProduceOperations);
+
+fn config(upper: bool, separator: Option<u8>) -> Config {
+    Config {
+        case: if upper { Case::Upper } else { Case::Lower },
+        separator,
+    }
+}
+
+// Produces all items, and checks that the remaining hint was correct before each of them.
+fn produce_all<P: Producer>(p: &mut P) -> (Vec<P::Item>, P::Error) {
+    let mut items = Vec::new();
+    let mut hints = Vec::new();
+    let e = loop {
+        hints.push(p.remaining_hint());
+        match p.produce() {
+            Ok(item) => items.push(item),
+            Err(e) => break e,
+        }
+    };
+
+    for (i, (lower, upper)) in hints.into_iter().enumerate() {
+        let remaining = items.len() - i;
+        assert!(lower <= remaining);
+        assert!(upper.map_or(true, |upper| remaining <= upper));
+    }
+    (items, e)
+}
+
+// Encodes the bytes, decodes the encoding, and checks that the result equals the bytes.
+fn round_trip(bytes: &[u8], config: Config, ops: Ops, cap: NonZeroUsize) {
+    let (c_ops, p_ops_in, p_ops_out) = ops;
+    let mut p = ScrambleProducer::new(pro::cursor(bytes), p_ops_in, cap);
+    let mut e = ScrambleConsumer::new(encoder(con::map_err(IntoVec::new(), |e| match e {}), config), c_ops, cap);
+    assert_eq!(bulk_consume_all(&mut p, &mut e), ());
+    assert_eq!(e.flush(), Ok(()));
+    let encoded = e.into_inner().into_inner().into_inner().into_vec();
+    let separators = if config.separator.is_some() { bytes.len().saturating_sub(1) } else { 0 };
+    assert_eq!(encoded.len(), bytes.len() * 2 + separators);
+
+    let mut d = ScrambleProducer::new(decoder(pro::cursor(&encoded[..]), config), p_ops_out, cap);
+    let (decoded, e) = produce_all(&mut d);
+    assert_eq!(&decoded[..], bytes);
+    assert_eq!(e, DecodeError::Inner(()));
+}
+
+// Dumps the bytes, and checks that there is a line for every 16 bytes.
+fn dump(bytes: &[u8]) {
+    let mut h = hexdump(IntoVec::new());
+    for byte in bytes {
+        assert_eq!(h.consume(*byte), Ok(()));
+    }
+    assert_eq!(h.flush(), Ok(()));
+
+    let dump = h.into_inner().into_vec();
+    let lines = dump.iter().filter(|c| **c == b'\n').count();
+    assert!(lines >= (bytes.len() + 15) / 16);
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Box<[u8]>, Box<[u8]>, bool, Option<u8>, Ops, NonZeroUsize)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((bytes, text, upper, separator, ops, cap)) => {
+            let cap = NonZeroUsize::new(min(cap.get(), 16)).unwrap();
+            // A separator that is a hex digit makes the text ambiguous.
+            let separator = separator.filter(|s| !s.is_ascii_hexdigit());
+            round_trip(&bytes[..], config(upper, separator), ops, cap);
+            dump(&bytes[..]);
+
+            // Decoding arbitrary text must neither panic nor misreport its remaining hint.
+            produce_all(&mut decoder(pro::cursor(&text[..]), config(upper, separator)));
+        }
+        _ => {}
+    }
+});
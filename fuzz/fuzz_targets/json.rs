@@ -0,0 +1,78 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use rw::pro::{self, Producer, BulkProducer, ProduceOperations, ScrambleProducer};
+use rw::json::*;
+
+// Lexes all tokens, merging the chunks of strings, and checks that the remaining hint was correct
+// before each token, that strings are valid UTF-8 and that numbers are valid numbers. Returns the
+// tokens with the merged chunks of strings, and the final error.
+fn lex_all<P: BulkProducer<Item = u8>>(l: &mut Lexer<P, Vec<u8>>, config: Config) -> (Vec<(JsonToken, Vec<u8>)>, LexError<P::Error>) {
+    let mut tokens: Vec<(JsonToken, Vec<u8>)> = Vec::new();
+    let mut hints = Vec::new();
+    let e = loop {
+        hints.push(l.remaining_hint());
+        match l.produce() {
+            Ok(JsonToken::Chunk(len)) => {
+                assert_eq!(l.chunk().len(), len);
+                assert!(len > 0);
+                match tokens.last_mut() {
+                    Some((JsonToken::Chunk(total), chunks)) => {
+                        *total += len;
+                        chunks.extend_from_slice(l.chunk());
+                    }
+                    _ => tokens.push((JsonToken::Chunk(len), l.chunk().to_vec())),
+                }
+            }
+            Ok(JsonToken::StringEnd) => {
+                if let Some((JsonToken::Chunk(_), chunks)) = tokens.last() {
+                    assert!(core::str::from_utf8(chunks).is_ok());
+                }
+                tokens.push((JsonToken::StringEnd, Vec::new()));
+            }
+            Ok(JsonToken::Number(len)) => {
+                assert_eq!(l.chunk().len(), len);
+                let text = core::str::from_utf8(l.chunk()).unwrap();
+                assert!(text.parse::<f64>().is_ok());
+                tokens.push((JsonToken::Number(len), l.chunk().to_vec()));
+            }
+            Ok(token) => {
+                assert!(l.chunk().is_empty());
+                tokens.push((token, Vec::new()));
+            }
+            Err(e) => break e,
+        }
+        assert!(l.depth() <= min(config.max_depth, MAX_DEPTH));
+    };
+
+    for (i, (_, upper)) in hints.iter().enumerate() {
+        assert!(upper.map_or(true, |upper| hints.len() - 1 - i <= upper));
+    }
+
+    // How much of a string that an error interrupts is produced depends on the slots.
+    if let Some((JsonToken::Chunk(_), _)) = tokens.last() {
+        tokens.pop();
+    }
+    (tokens, e)
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Box<[u8]>, u16, ProduceOperations, NonZeroUsize)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((bytes, max_depth, p_ops, cap)) => {
+            let config = Config { max_depth: max_depth as usize };
+            let cap = NonZeroUsize::new(min(cap.get(), 2048)).unwrap();
+
+            // Lexing arbitrary bytes must not panic, and must not depend on how the input is split
+            // into slots, except for the lengths of the chunks of strings.
+            let buffer = vec![0; bytes.len() + 4];
+            let (tokens, e) = lex_all(&mut lexer(pro::cursor(&bytes[..]), buffer.clone(), config), config);
+            let mut scrambled = lexer(ScrambleProducer::new(pro::cursor(&bytes[..]), p_ops, cap), buffer, config);
+            assert_eq!(lex_all(&mut scrambled, config), (tokens, e));
+        }
+        _ => {}
+    }
+});
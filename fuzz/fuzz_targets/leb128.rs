@@ -0,0 +1,79 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use rw::pro::*;
+use rw::leb128::*;
+
+// Encodes the integers, decodes the encodings, and checks that the result equals the integers.
+fn round_trip<V: Variant + Copy>(ints: &[V::Int], variant: V, ops: ProduceOperations, cap: NonZeroUsize) where
+    V::Int: PartialEq + core::fmt::Debug,
+{
+    let encoded = ScrambleProducer::new(encoder(from_iter(ints.iter().copied()), variant), ops, cap);
+    let mut d = decoder(encoded, variant);
+    for int in ints.iter() {
+        assert_eq!(d.produce(), Ok(*int));
+    }
+    assert_eq!(d.produce(), Err(DecodeError::Inner(())));
+}
+
+// Decodes arbitrary bytes, and checks that every decoded integer was encoded canonically, i.e.
+// that its bytes in the input are exactly the bytes of its encoding.
+fn decode_arbitrary<V: Variant + Copy>(bytes: &[u8], variant: V) where
+    V::Int: PartialEq + core::fmt::Debug,
+{
+    let mut d = decoder(cursor(bytes), variant);
+    let mut start = 0;
+    let mut skipped = false;
+    loop {
+        let result = d.produce();
+        let end = bytes.len() - d.as_ref().remaining_hint().0;
+        match result {
+            Ok(int) => {
+                // After an overflow, the bytes of the current integer are preceded by the skipped
+                // ones.
+                if !skipped {
+                    let mut e = encoder(from_iter(core::iter::once(int)), variant);
+                    let mut encoding = Vec::new();
+                    while let Ok(byte) = e.produce() {
+                        encoding.push(byte);
+                    }
+                    assert!(encoding.len() <= MAX_LEN);
+                    assert_eq!(&bytes[start..end], &encoding[..]);
+                }
+                skipped = false;
+            }
+            Err(DecodeError::Overlong) => skipped = false,
+            Err(DecodeError::Overflow) => {
+                if !skipped {
+                    assert_eq!(end - start, MAX_LEN);
+                }
+                skipped = true;
+            }
+            Err(DecodeError::Incomplete) | Err(DecodeError::Inner(())) => {
+                assert_eq!(end, bytes.len());
+                return;
+            }
+        }
+        start = end;
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Box<[u64]>, Box<[i64]>, Box<[u8]>, ProduceOperations, ProduceOperations, ProduceOperations, NonZeroUsize)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((unsigned, signed, bytes, ops_a, ops_b, ops_c, cap)) => {
+            let cap = NonZeroUsize::new(min(cap.get(), 2 * MAX_LEN)).unwrap();
+            round_trip(&unsigned[..], Unsigned, ops_a, cap);
+            round_trip(&signed[..], Signed, ops_b, cap);
+            round_trip(&signed[..], ZigZag, ops_c, cap);
+
+            decode_arbitrary(&bytes[..], Unsigned);
+            decode_arbitrary(&bytes[..], Signed);
+            decode_arbitrary(&bytes[..], ZigZag);
+        }
+        _ => {}
+    }
+});
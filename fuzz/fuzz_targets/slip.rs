@@ -0,0 +1,66 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use arbitrary::{Arbitrary, Unstructured};
+
+use core::cmp::min;
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use rw::con::{Consumer, ConsumeOperations, IntoVec, ScrambleConsumer};
+use rw::pro::{self, Producer, ProduceOperations, ScrambleProducer};
+use rw::slip::*;
+
+// Encodes the frames, decodes the encodings, and checks that the results equal the frames that
+// are neither empty nor too long.
+fn round_trip(frames: &[Vec<u8>], config: Config, c_ops: ConsumeOperations, p_ops: ProduceOperations, cap: NonZeroUsize) {
+    let mut e = encoder(ScrambleConsumer::new(IntoVec::new(), c_ops, cap), config);
+    for frame in frames {
+        if frame.len() > config.max_len {
+            assert_eq!(e.consume(frame), Err(EncodeError::TooLong));
+        } else {
+            assert_eq!(e.consume(frame), Ok(()));
+        }
+    }
+    assert_eq!(e.flush(), Ok(()));
+    let encoded = e.into_inner().into_inner().into_vec();
+
+    let expected: Vec<&Vec<u8>> = frames.iter().filter(|frame| !frame.is_empty() && frame.len() <= config.max_len).collect();
+    let mut d = decoder(ScrambleProducer::new(pro::cursor(&encoded[..]), p_ops, cap), vec![0; config.max_len], config);
+    for (i, frame) in expected.iter().enumerate() {
+        assert!(d.remaining_hint().1.map_or(true, |upper| expected.len() - i <= upper));
+        assert_eq!(d.produce(), Ok(frame.len()));
+        assert_eq!(d.frame(), &frame[..]);
+    }
+    assert_eq!(d.produce(), Err(DecodeError::Inner(())));
+}
+
+// Decodes arbitrary bytes, and checks that the decoder neither panics nor misreports its
+// remaining hint, and that it can continue after errors within frames.
+fn decode_arbitrary(bytes: &[u8], config: Config, buffer_len: usize) {
+    let mut d = decoder(pro::cursor(bytes), vec![0; buffer_len], config);
+    let mut hints = Vec::new();
+    loop {
+        hints.push(d.remaining_hint());
+        match d.produce() {
+            Ok(len) => assert!(0 < len && len <= min(buffer_len, config.max_len)),
+            Err(DecodeError::InvalidEscape(_)) | Err(DecodeError::TooLong) => {}
+            Err(DecodeError::Inner(())) | Err(DecodeError::Incomplete) => break,
+        }
+    }
+
+    // Every frame takes at least its terminating `END`.
+    assert!(hints[0].1.map_or(true, |upper| hints.len() - 1 <= upper));
+}
+
+fuzz_target!(|data: &[u8]| {
+    match <(Vec<Vec<u8>>, Box<[u8]>, bool, u16, ConsumeOperations, ProduceOperations, NonZeroUsize, u8)>::arbitrary(&mut Unstructured::new(data)) {
+        Ok((frames, bytes, leading_end, max_len, c_ops, p_ops, cap, buffer_len)) => {
+            let config = Config { leading_end, max_len: max_len as usize };
+            let cap = NonZeroUsize::new(min(cap.get(), 2048)).unwrap();
+            round_trip(&frames[..], config, c_ops, p_ops, cap);
+            decode_arbitrary(&bytes[..], config, buffer_len as usize);
+        }
+        _ => {}
+    }
+});
@@ -0,0 +1,155 @@
+//! Support for custom allocators.
+//!
+//! The collection-backed producers and consumers (`Repeat`, `IntoVec`, `AppendVec` and
+//! `AppendVecDeque`), the buffers of the scramble adapters and the broadcast buffer are generic
+//! over an `Allocator`, defaulting to `Global`. Everything else that allocates, e.g. the `mpsc`
+//! channel and `spawn_pipe`, uses the global allocator.
+//!
+//! The operation schedules of the scramble adapters are out of scope: they are always allocated
+//! with the global allocator, including by `new_in` and `from_seed_in`. The scramble adapters are
+//! testing tools, a pipeline that must never touch the global allocator cannot contain them.
+//!
+//! With the `unstable` feature, this module re-exports the `Allocator` trait and the `Global`
+//! allocator of the standard library, and the allocator-generic types offer `new_in`-style
+//! constructors. Without it, the same names refer to stand-ins that only allow for the global
+//! allocator, so that the allocator-generic types have the same shape on stable Rust.
+extern crate maybe_std as base;
+
+use base::boxed::Box;
+use core::mem::MaybeUninit;
+
+#[cfg(feature = "unstable")]
+use base::collections::VecDeque;
+#[cfg(feature = "unstable")]
+use base::vec::Vec;
+
+#[cfg(not(feature = "unstable"))]
+use base::vec::Vec;
+#[cfg(not(feature = "unstable"))]
+use core::marker::PhantomData;
+#[cfg(not(feature = "unstable"))]
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "unstable")]
+pub use base::alloc::{Allocator, Global};
+
+#[cfg(not(feature = "unstable"))]
+pub use stand_in::{Allocator, Global};
+
+#[cfg(not(feature = "unstable"))]
+mod stand_in {
+    /// Stand-in for the unstable `Allocator` trait of the standard library. Only implemented by
+    /// `Global`.
+    pub trait Allocator: private::Sealed {}
+
+    /// Stand-in for the unstable `Global` allocator of the standard library.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Global;
+
+    impl Allocator for Global {}
+
+    mod private {
+        pub trait Sealed {}
+
+        impl Sealed for super::Global {}
+    }
+}
+
+/// A collection of the standard library that uses the global allocator, tagged with an
+/// `Allocator` so that it can stand in for the allocator-generic version of the collection.
+#[cfg(not(feature = "unstable"))]
+#[derive(Debug)]
+#[repr(transparent)]
+pub(crate) struct InGlobal<C: ?Sized, A>(PhantomData<A>, C);
+
+#[cfg(not(feature = "unstable"))]
+impl<C: ?Sized, A> Deref for InGlobal<C, A> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.1
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<C: ?Sized, A> DerefMut for InGlobal<C, A> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.1
+    }
+}
+
+/// A `Box` whose memory is managed by `A`.
+#[cfg(feature = "unstable")]
+pub(crate) type BoxIn<T, A> = Box<T, A>;
+/// A `Box` whose memory is managed by `A`.
+#[cfg(not(feature = "unstable"))]
+pub(crate) type BoxIn<T, A> = InGlobal<Box<T>, A>;
+
+/// A `Vec` whose memory is managed by `A`.
+#[cfg(feature = "unstable")]
+pub(crate) type VecIn<T, A> = Vec<T, A>;
+/// A `Vec` whose memory is managed by `A`.
+#[cfg(not(feature = "unstable"))]
+pub(crate) type VecIn<T, A> = InGlobal<Vec<T>, A>;
+
+/// A `VecDeque` whose memory is managed by `A`.
+#[cfg(feature = "unstable")]
+pub(crate) type VecDequeIn<T, A> = VecDeque<T, A>;
+/// A `VecDeque` whose memory is managed by `A`.
+#[cfg(not(feature = "unstable"))]
+pub(crate) type VecDequeIn<T, A> = InGlobal<base::collections::VecDeque<T>, A>;
+
+/// Turns a collection using the global allocator into the corresponding `BoxIn`, `VecIn` or
+/// `VecDequeIn`.
+#[cfg(feature = "unstable")]
+pub(crate) fn in_global<C>(c: C) -> C {
+    c
+}
+/// Turns a collection using the global allocator into the corresponding `BoxIn`, `VecIn` or
+/// `VecDequeIn`.
+#[cfg(not(feature = "unstable"))]
+pub(crate) fn in_global<C>(c: C) -> InGlobal<C, Global> {
+    InGlobal(PhantomData, c)
+}
+
+/// Turns a mutable reference to a collection using the global allocator into one to the
+/// corresponding `BoxIn`, `VecIn` or `VecDequeIn`.
+#[cfg(not(feature = "unstable"))]
+pub(crate) fn in_global_mut<C: ?Sized>(c: &mut C) -> &mut InGlobal<C, Global> {
+    // `InGlobal` is a transparent wrapper around `C`.
+    unsafe { &mut *(c as *mut C as *mut InGlobal<C, Global>) }
+}
+
+/// The inverse of `in_global`.
+#[cfg(not(feature = "unstable"))]
+pub(crate) fn out_of_global<C>(c: InGlobal<C, Global>) -> C {
+    c.1
+}
+
+/// Allocates a slice of the given length with the global allocator.
+pub(crate) fn new_uninit_slice<T>(len: usize) -> BoxIn<[MaybeUninit<T>], Global> {
+    #[cfg(feature = "unstable")]
+    return Box::new_uninit_slice(len);
+    #[cfg(not(feature = "unstable"))]
+    {
+        let mut data = Vec::with_capacity(len);
+        data.resize_with(len, MaybeUninit::uninit);
+        return in_global(data.into_boxed_slice());
+    }
+}
+
+/// Allocates a slice of the given length with the given allocator.
+#[cfg(feature = "unstable")]
+pub(crate) fn new_uninit_slice_in<T, A>(len: usize, alloc: A) -> BoxIn<[MaybeUninit<T>], A> where
+    A: Allocator,
+{
+    Box::new_uninit_slice_in(len, alloc)
+}
+/// Allocates a slice of the given length with the given allocator.
+#[cfg(all(not(feature = "unstable"), feature = "std"))]
+pub(crate) fn new_uninit_slice_in<T, A>(len: usize, _alloc: A) -> BoxIn<[MaybeUninit<T>], A> where
+    A: Allocator,
+{
+    // The stand-in `Allocator` is only implemented by `Global`.
+    InGlobal(PhantomData, new_uninit_slice::<T>(len).1)
+}
@@ -0,0 +1,472 @@
+//! Streaming Base64 encoding and decoding, as specified in RFC 4648.
+//!
+//! An `Encoder` is a `BulkConsumer` of raw bytes that writes their Base64 encoding into a wrapped
+//! `BulkConsumer`, a `Decoder` is a `BulkProducer` that produces the bytes encoded by the Base64
+//! text of a wrapped `BulkProducer`. Groups of bytes or characters may be split arbitrarily
+//! across the slots of the wrapped values.
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::*;
+
+/// The two alphabets of RFC 4648.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// Uses `+` and `/` as the last two characters.
+    Standard,
+    /// Uses `-` and `_` as the last two characters, so that the text can be used in URLs and
+    /// file names.
+    UrlSafe,
+}
+
+/// Configures the Base64 variant to encode or decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    pub alphabet: Alphabet,
+    /// Whether the encoded text is padded with `=` to a multiple of four characters. A decoder
+    /// with padding rejects unpadded text and vice versa.
+    pub padding: bool,
+}
+
+impl Config {
+    pub const STANDARD: Config = Config { alphabet: Alphabet::Standard, padding: true };
+    pub const STANDARD_NO_PAD: Config = Config { alphabet: Alphabet::Standard, padding: false };
+    pub const URL_SAFE: Config = Config { alphabet: Alphabet::UrlSafe, padding: true };
+    pub const URL_SAFE_NO_PAD: Config = Config { alphabet: Alphabet::UrlSafe, padding: false };
+
+    fn encode_sextet(&self, sextet: u8) -> u8 {
+        match sextet {
+            0..=25 => b'A' + sextet,
+            26..=51 => b'a' + (sextet - 26),
+            52..=61 => b'0' + (sextet - 52),
+            62 => if self.alphabet == Alphabet::Standard { b'+' } else { b'-' },
+            _ => if self.alphabet == Alphabet::Standard { b'/' } else { b'_' },
+        }
+    }
+
+    fn decode_sextet(&self, c: u8) -> Option<u8> {
+        match (c, self.alphabet) {
+            (b'A'..=b'Z', _) => Some(c - b'A'),
+            (b'a'..=b'z', _) => Some(c - b'a' + 26),
+            (b'0'..=b'9', _) => Some(c - b'0' + 52),
+            (b'+', Alphabet::Standard) | (b'-', Alphabet::UrlSafe) => Some(62),
+            (b'/', Alphabet::Standard) | (b'_', Alphabet::UrlSafe) => Some(63),
+            _ => None,
+        }
+    }
+
+    // Encodes one to three bytes into `out`, returns the number of characters.
+    fn encode_group(&self, bytes: &[u8], out: &mut [u8]) -> usize {
+        let b0 = bytes[0];
+        let b1 = bytes.get(1).copied().unwrap_or(0);
+        let b2 = bytes.get(2).copied().unwrap_or(0);
+        let sextets = [b0 >> 2, ((b0 & 0x03) << 4) | (b1 >> 4), ((b1 & 0x0f) << 2) | (b2 >> 6), b2 & 0x3f];
+
+        let chars = bytes.len() + 1;
+        for i in 0..chars {
+            out[i] = self.encode_sextet(sextets[i]);
+        }
+
+        if self.padding {
+            for i in chars..4 {
+                out[i] = b'=';
+            }
+            4
+        } else {
+            chars
+        }
+    }
+}
+
+/// Creates an encoder that writes the Base64 encoding of all consumed bytes into `inner`.
+pub fn encoder<C>(inner: C, config: Config) -> Encoder<C> {
+    Encoder {
+        inner,
+        config,
+        input: [MaybeUninit::uninit(); 3],
+        input_len: 0,
+        output: [0; 4],
+        output_start: 0,
+        output_end: 0,
+    }
+}
+
+/// Consumes bytes and writes their Base64 encoding into a wrapped `BulkConsumer`.
+///
+/// Flushing writes the final, possibly padded group, so it ends the Base64 text. Consuming
+/// further bytes afterwards starts a new text, which is simply appended to the previous one.
+pub struct Encoder<C> {
+    inner: C,
+    config: Config,
+    // bytes of an incomplete group
+    input: [MaybeUninit<u8>; 3],
+    input_len: usize,
+    // characters that have been encoded but not yet consumed by the inner consumer
+    output: [u8; 4],
+    output_start: usize,
+    output_end: usize,
+}
+
+impl<C: BulkConsumer<Item = u8>> Encoder<C> {
+    // Writes all pending characters into the inner consumer.
+    fn drain(&mut self) -> Result<(), C::Error> {
+        while let Some(chars) = Slice1::from_slice(&self.output[self.output_start..self.output_end]) {
+            self.output_start += self.inner.bulk_consume(chars)?.get();
+        }
+        Ok(())
+    }
+
+    // Encodes the buffered input bytes into the pending characters.
+    fn encode_input(&mut self) {
+        let input = unsafe { slice_assume_init_ref(&self.input[..self.input_len]) };
+        self.output_end = self.config.encode_group(input, &mut self.output);
+        self.output_start = 0;
+        self.input_len = 0;
+    }
+}
+
+impl<C> Wrapper<C> for Encoder<C> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C> AsRef<C> for Encoder<C> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C> AsMut<C> for Encoder<C> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: BulkConsumer<Item = u8>> Consumer for Encoder<C> {
+    type Item = u8;
+    type Error = C::Error;
+
+    fn consume(&mut self, item: u8) -> Result<(), Self::Error> {
+        self.drain()?;
+        self.input[self.input_len] = MaybeUninit::new(item);
+        self.input_len += 1;
+        if self.input_len == 3 {
+            self.encode_input();
+            self.drain()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.drain()?;
+        if self.input_len > 0 {
+            self.encode_input();
+            self.drain()?;
+        }
+        self.inner.flush()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve((additional / 3).saturating_mul(4).saturating_add(4))
+    }
+}
+
+impl<C: BulkConsumer<Item = u8>> BulkConsumer for Encoder<C> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        self.drain()?;
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(&mut self.input[self.input_len..]) })
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.input_len += amount.get();
+        if self.input_len == 3 {
+            // The characters are written by the next call.
+            self.encode_input();
+        }
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        self.drain()?;
+
+        if self.input_len == 0 && data.len_() >= 3 {
+            // Encode as many complete groups as possible directly into the inner consumer.
+            let slots = self.inner.consumer_slots()?;
+            let groups = min(data.len_() / 3, slots.len_() / 4);
+            if groups > 0 {
+                let mut chars = [0; 4];
+                for i in 0..groups {
+                    self.config.encode_group(&data[i * 3..i * 3 + 3], &mut chars);
+                    write_slice(&mut slots[i * 4..i * 4 + 4], &chars);
+                }
+                unsafe {
+                    self.inner.did_consume(NonZeroUsize::new_unchecked(groups * 4));
+                    return Ok(NonZeroUsize::new_unchecked(groups * 3));
+                }
+            }
+        }
+
+        let amount = min(3 - self.input_len, data.len_());
+        write_slice(&mut self.input[self.input_len..self.input_len + amount], &data[..amount]);
+        self.input_len += amount;
+        if self.input_len == 3 {
+            // The characters are written by the next call, so that an error of the inner consumer
+            // is reported by the call that caused it.
+            self.encode_input();
+        }
+        Ok(unsafe { NonZeroUsize::new_unchecked(amount) })
+    }
+}
+
+/// Emitted by a `Decoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError<E> {
+    /// The wrapped producer emitted an error, e.g. because the text has ended. All bytes encoded
+    /// by the text have been produced before.
+    Inner(E),
+    /// The text contains a byte that is not part of the alphabet.
+    InvalidByte(u8),
+    /// The text contains padding in an invalid position, or lacks padding although the decoder
+    /// requires it.
+    InvalidPadding,
+    /// The text ended in the middle of a group.
+    IncompleteGroup,
+    /// The last group of the text encodes nonzero bits that do not belong to any byte, so the text
+    /// is not the canonical encoding of any bytes.
+    TrailingBits,
+}
+
+// The number of decoded bytes a decoder buffers at most.
+const DECODED_LEN: usize = 48;
+
+struct DecodeState {
+    config: Config,
+    // sextets of an incomplete group
+    group: [u8; 4],
+    group_len: usize,
+    // number of padding characters in the current group
+    padding: usize,
+    // whether a padded group has ended the text
+    done: bool,
+    // decoded bytes that have not been produced yet
+    decoded: [u8; DECODED_LEN],
+    decoded_start: usize,
+    decoded_end: usize,
+}
+
+impl DecodeState {
+    fn push<E>(&mut self, c: u8) -> Result<(), DecodeError<E>> {
+        if self.done {
+            return Err(DecodeError::InvalidPadding);
+        }
+
+        if c == b'=' {
+            if !self.config.padding {
+                return Err(DecodeError::InvalidByte(c));
+            } else if self.group_len < 2 {
+                return Err(DecodeError::InvalidPadding);
+            }
+
+            self.padding += 1;
+            if self.group_len + self.padding == 4 {
+                self.done = true;
+                return self.decode_group();
+            }
+            return Ok(());
+        } else if self.padding > 0 {
+            return Err(DecodeError::InvalidPadding);
+        }
+
+        match self.config.decode_sextet(c) {
+            Some(sextet) => {
+                self.group[self.group_len] = sextet;
+                self.group_len += 1;
+                if self.group_len == 4 {
+                    self.decode_group()
+                } else {
+                    Ok(())
+                }
+            }
+            None => Err(DecodeError::InvalidByte(c)),
+        }
+    }
+
+    // Called once the text has ended.
+    fn finish<E>(&mut self) -> Result<(), DecodeError<E>> {
+        if self.padding > 0 && !self.done {
+            Err(DecodeError::IncompleteGroup)
+        } else if self.group_len == 0 {
+            Ok(())
+        } else if self.config.padding {
+            Err(DecodeError::InvalidPadding)
+        } else if self.group_len == 1 {
+            Err(DecodeError::IncompleteGroup)
+        } else {
+            self.decode_group()
+        }
+    }
+
+    // Decodes the sextets of the current group, of which there are at least two.
+    fn decode_group<E>(&mut self) -> Result<(), DecodeError<E>> {
+        for i in self.group_len..4 {
+            self.group[i] = 0;
+        }
+        let g = self.group;
+        let bytes = [(g[0] << 2) | (g[1] >> 4), (g[1] << 4) | (g[2] >> 2), (g[2] << 6) | g[3]];
+
+        let amount = self.group_len - 1;
+        let trailing = match amount {
+            1 => g[1] & 0x0f,
+            2 => g[2] & 0x03,
+            _ => 0,
+        };
+        if trailing != 0 {
+            return Err(DecodeError::TrailingBits);
+        }
+
+        self.decoded[self.decoded_end..self.decoded_end + amount].copy_from_slice(&bytes[..amount]);
+        self.decoded_end += amount;
+        self.group_len = 0;
+        Ok(())
+    }
+}
+
+/// Creates a decoder that produces the bytes encoded by the Base64 text that `inner` produces.
+pub fn decoder<P: Producer>(inner: P, config: Config) -> Decoder<P> {
+    Decoder {
+        inner,
+        state: DecodeState {
+            config,
+            group: [0; 4],
+            group_len: 0,
+            padding: 0,
+            done: false,
+            decoded: [0; DECODED_LEN],
+            decoded_start: 0,
+            decoded_end: 0,
+        },
+        err: None,
+    }
+}
+
+/// Produces the bytes encoded by the Base64 text of a wrapped `BulkProducer`. The text ends when
+/// the wrapped producer emits an error.
+pub struct Decoder<P: Producer> {
+    inner: P,
+    state: DecodeState,
+    // the error that ended the text or occurred while decoding it, emitted once all bytes decoded
+    // before it have been produced
+    err: Option<DecodeError<P::Error>>,
+}
+
+impl<P: BulkProducer<Item = u8>> Decoder<P> {
+    // Decodes further bytes, if there are no decoded bytes left.
+    fn fill(&mut self) -> Result<(), DecodeError<P::Error>> {
+        if self.state.decoded_start < self.state.decoded_end {
+            return Ok(());
+        } else if let Some(e) = self.err.take() {
+            return Err(e);
+        }
+
+        self.state.decoded_start = 0;
+        self.state.decoded_end = 0;
+        while self.state.decoded_end == 0 {
+            let chars = match self.inner.producer_slots() {
+                Ok(chars) => chars,
+                Err(e) => {
+                    self.state.finish()?;
+                    if self.state.decoded_end == 0 {
+                        return Err(DecodeError::Inner(e));
+                    }
+                    self.err = Some(DecodeError::Inner(e));
+                    return Ok(());
+                }
+            };
+
+            let mut amount = 0;
+            let mut result = Ok(());
+            for c in chars.iter() {
+                if self.state.decoded_end + 3 > DECODED_LEN {
+                    break;
+                }
+                result = self.state.push(*c);
+                if result.is_err() {
+                    break;
+                }
+                amount += 1;
+            }
+            if let Some(amount) = NonZeroUsize::new(amount) {
+                self.inner.did_produce(amount);
+            }
+            if let Err(e) = result {
+                if self.state.decoded_end == 0 {
+                    return Err(e);
+                }
+                self.err = Some(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P: Producer> Wrapper<P> for Decoder<P> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Producer> AsRef<P> for Decoder<P> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P: Producer> AsMut<P> for Decoder<P> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>> Producer for Decoder<P> {
+    type Item = u8;
+    type Error = DecodeError<P::Error>;
+
+    fn produce(&mut self) -> Result<u8, Self::Error> {
+        self.fill()?;
+        let item = self.state.decoded[self.state.decoded_start];
+        self.state.decoded_start += 1;
+        Ok(item)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(DecodeError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let decoded = self.state.decoded_end - self.state.decoded_start;
+        match self.inner.remaining_hint().1 {
+            Some(chars) if self.err.is_none() => {
+                let upper = chars.checked_add(self.state.group_len).map(|chars| chars / 4 * 3);
+                (decoded, upper.and_then(|upper| upper.checked_add(decoded + 2)))
+            }
+            Some(_) => (decoded, Some(decoded)),
+            None => (decoded, None),
+        }
+    }
+}
+
+impl<P: BulkProducer<Item = u8>> BulkProducer for Decoder<P> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        self.fill()?;
+        Ok(unsafe { Slice1::from_slice_unchecked(&self.state.decoded[self.state.decoded_start..self.state.decoded_end]) })
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.state.decoded_start += amount.get();
+    }
+}
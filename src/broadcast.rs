@@ -0,0 +1,335 @@
+//! A blocking broadcast buffer with a single writer and any number of readers.
+//!
+//! The `Writer` is a `BulkConsumer`, each `Reader` is a `BulkProducer` that produces every item
+//! the writer consumes after the reader was created. All readers share a single ring buffer,
+//! in which each reader keeps its own position.
+//!
+//! A buffer created via `channel` throttles the writer: it blocks while the slowest reader is a
+//! full buffer behind. A buffer created via `lossy_channel` never blocks the writer but overwrites
+//! the oldest items instead; readers that miss items due to this emit `Lagged` once and then
+//! continue with the oldest item still available.
+//!
+//! The ring buffer and the staging buffers of the handles are allocated by an `Allocator`, the
+//! `channel_in` and `lossy_channel_in` functions allow for custom allocators with the `unstable`
+//! feature.
+extern crate maybe_std as base;
+
+use base::mem::MaybeUninit;
+use base::num::NonZeroUsize;
+use base::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use base::vec::Vec;
+use core::cmp::min;
+
+use slice_n::Slice1;
+
+use crate::*;
+use crate::allocator::{new_uninit_slice_in, Allocator, BoxIn, Global};
+use crate::ringbuffer::FixedBuffer;
+
+/// Creates a broadcast buffer of the given capacity whose writer is throttled by the slowest
+/// reader. Readers are obtained via `Writer::subscribe`.
+pub fn channel<T: Copy>(capacity: NonZeroUsize) -> Writer<T> {
+    new_writer(capacity, false, Global)
+}
+
+/// Creates a broadcast buffer of the given capacity whose writer is never throttled but instead
+/// overwrites items that some readers have not read yet. Readers are obtained via
+/// `Writer::subscribe`.
+pub fn lossy_channel<T: Copy>(capacity: NonZeroUsize) -> Writer<T> {
+    new_writer(capacity, true, Global)
+}
+
+/// Like `channel`, but allocates the ring buffer and the staging buffers of the writer and the
+/// readers with the given allocator.
+#[cfg(feature = "unstable")]
+pub fn channel_in<T: Copy, A: Allocator + Clone>(capacity: NonZeroUsize, alloc: A) -> Writer<T, A> {
+    new_writer(capacity, false, alloc)
+}
+
+/// Like `lossy_channel`, but allocates the ring buffer and the staging buffers of the writer and
+/// the readers with the given allocator.
+#[cfg(feature = "unstable")]
+pub fn lossy_channel_in<T: Copy, A: Allocator + Clone>(capacity: NonZeroUsize, alloc: A) -> Writer<T, A> {
+    new_writer(capacity, true, alloc)
+}
+
+fn new_writer<T: Copy, A: Allocator + Clone>(capacity: NonZeroUsize, lossy: bool, alloc: A) -> Writer<T, A> {
+    Writer {
+        shared: Arc::new(Shared {
+            state: Mutex::new(State {
+                data: new_uninit_slice_in(capacity.get(), alloc.clone()),
+                written: 0,
+                cursors: Vec::new(),
+                lossy,
+                writer: true,
+            }),
+            readable: Condvar::new(),
+            writable: Condvar::new(),
+        }),
+        staging: FixedBuffer::new_in(capacity, alloc.clone()),
+        alloc,
+    }
+}
+
+#[derive(Debug)]
+struct Shared<T, A: Allocator> {
+    state: Mutex<State<T, A>>,
+    // signalled when items have been written or the writer has been dropped
+    readable: Condvar,
+    // signalled when a reader has advanced or has been dropped
+    writable: Condvar,
+}
+
+#[derive(Debug)]
+struct State<T, A: Allocator> {
+    data: BoxIn<[MaybeUninit<T>], A>,
+    // total number of items ever written, the next item goes to `written % capacity`
+    written: u64,
+    // the total number of items each reader has read, indexed by reader id, `None` for free ids
+    cursors: Vec<Option<u64>>,
+    lossy: bool,
+    // whether the writer is still alive
+    writer: bool,
+}
+
+impl<T, A: Allocator> Shared<T, A> {
+    fn lock(&self) -> MutexGuard<'_, State<T, A>> {
+        // The state is consistent whenever the lock is released, even when panicking.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Copy, A: Allocator> Shared<T, A> {
+    // Blocks until at least one item can be written.
+    fn wait_for_space(&self) -> MutexGuard<'_, State<T, A>> {
+        let mut state = self.lock();
+        while state.free() == 0 {
+            state = self.writable.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state
+    }
+}
+
+impl<T: Copy, A: Allocator> State<T, A> {
+    fn capacity(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    // The number of items that can be written without overwriting unread items.
+    fn free(&self) -> u64 {
+        match self.cursors.iter().flatten().min() {
+            Some(slowest) if !self.lossy => self.capacity() - (self.written - slowest),
+            _ => self.capacity(),
+        }
+    }
+
+    // Writes a nonzero number of items from `items` into the ring, returns how many.
+    fn write(&mut self, items: &Slice1<T>) -> NonZeroUsize {
+        let start = (self.written % self.capacity()) as usize;
+        let amount = min(min(items.len_() as u64, self.free()) as usize, self.data.len() - start);
+        write_slice(&mut self.data[start..start + amount], &items[..amount]);
+        self.written += amount as u64;
+        unsafe { NonZeroUsize::new_unchecked(amount) }
+    }
+}
+
+/// The writing half of a broadcast buffer.
+///
+/// Items are handed to the readers as soon as they have been consumed, so flushing is not
+/// required for the readers to see them.
+#[derive(Debug)]
+pub struct Writer<T, A: Allocator = Global> {
+    shared: Arc<Shared<T, A>>,
+    // holds the items written via `consumer_slots` until `did_consume` hands them to the readers
+    staging: FixedBuffer<T, A>,
+    // allocates the staging buffers of new readers
+    alloc: A,
+}
+
+impl<T: Copy, A: Allocator + Clone> Writer<T, A> {
+    /// Creates a new `Reader` that produces all items consumed by this writer from now on.
+    pub fn subscribe(&self) -> Reader<T, A> {
+        let mut state = self.shared.lock();
+        let cursor = Some(state.written);
+        let id = match state.cursors.iter().position(Option::is_none) {
+            Some(id) => {
+                state.cursors[id] = cursor;
+                id
+            }
+            None => {
+                state.cursors.push(cursor);
+                state.cursors.len() - 1
+            }
+        };
+
+        Reader {
+            shared: self.shared.clone(),
+            id,
+            staging: FixedBuffer::new_in(self.staging.get_capacity(), self.alloc.clone()),
+        }
+    }
+}
+
+impl<T: Copy, A: Allocator> Consumer for Writer<T, A> {
+    type Item = T;
+    type Error = Infallible;
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        let _ = self.bulk_consume(unsafe { Slice1::from_slice_unchecked(core::slice::from_ref(&item)) });
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T: Copy, A: Allocator> BulkConsumer for Writer<T, A> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        Ok(self.staging.consumer_slots().unwrap())
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.staging.did_consume(amount);
+
+        while let Ok(items) = self.staging.producer_slots() {
+            let amount = self.shared.wait_for_space().write(items);
+            self.shared.readable.notify_all();
+            self.staging.did_produce(amount);
+        }
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        let amount = self.shared.wait_for_space().write(data);
+        self.shared.readable.notify_all();
+        Ok(amount)
+    }
+}
+
+impl<T, A: Allocator> Drop for Writer<T, A> {
+    fn drop(&mut self) {
+        self.shared.lock().writer = false;
+        self.shared.readable.notify_all();
+    }
+}
+
+/// Emitted by a `Reader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadError {
+    /// The writer has been dropped and all its items have been read.
+    Closed,
+    /// The writer of a lossy buffer has overwritten this many items before the reader could read
+    /// them. The reader can continue producing afterwards, starting with the oldest item that is
+    /// still available.
+    Lagged(u64),
+}
+
+/// A reading handle of a broadcast buffer. Blocks while it has read all items written so far.
+#[derive(Debug)]
+pub struct Reader<T, A: Allocator = Global> {
+    shared: Arc<Shared<T, A>>,
+    // index into the cursors of the shared state
+    id: usize,
+    // holds items that have been copied out of the ring but not yet produced
+    staging: FixedBuffer<T, A>,
+}
+
+impl<T: Copy, A: Allocator> Reader<T, A> {
+    // Copies as many unread items as possible into the staging buffer, optionally blocking until
+    // there is at least one.
+    fn fill(&mut self, block: bool) -> Result<(), ReadError> {
+        let mut state = self.shared.lock();
+        let mut cursor;
+        loop {
+            cursor = state.cursors[self.id].unwrap();
+            if state.written - cursor > state.capacity() {
+                let lag = state.written - cursor - state.capacity();
+                state.cursors[self.id] = Some(cursor + lag);
+                return Err(ReadError::Lagged(lag));
+            } else if cursor < state.written || !block {
+                break;
+            } else if !state.writer {
+                return Err(ReadError::Closed);
+            }
+            state = self.shared.readable.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+
+        while cursor < state.written {
+            let slots = match self.staging.consumer_slots() {
+                Ok(slots) => slots,
+                Err(()) => break,
+            };
+            let start = (cursor % state.capacity()) as usize;
+            let amount = min(min(slots.len_() as u64, state.written - cursor) as usize, state.data.len() - start);
+            slots[..amount].copy_from_slice(&state.data[start..start + amount]);
+            unsafe { self.staging.did_consume(NonZeroUsize::new_unchecked(amount)) };
+            cursor += amount as u64;
+        }
+
+        state.cursors[self.id] = Some(cursor);
+        self.shared.writable.notify_one();
+        Ok(())
+    }
+}
+
+impl<T: Copy, A: Allocator> Producer for Reader<T, A> {
+    type Item = T;
+    type Error = ReadError;
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        if self.staging.get_amount() == 0 {
+            self.fill(true)?;
+        }
+
+        Ok(self.staging.produce().unwrap())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        if self.staging.get_amount() == 0 {
+            self.fill(false)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let state = self.shared.lock();
+        let buffered = self.staging.get_amount();
+        if state.lossy {
+            // Unread items in the ring might be overwritten at any time.
+            return (buffered, None);
+        }
+
+        let unread = buffered + (state.written - state.cursors[self.id].unwrap()) as usize;
+        if state.writer {
+            (unread, None)
+        } else {
+            (unread, Some(unread))
+        }
+    }
+}
+
+impl<T: Copy, A: Allocator> BulkProducer for Reader<T, A> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        if self.staging.get_amount() == 0 {
+            self.fill(true)?;
+        }
+
+        Ok(self.staging.producer_slots().unwrap())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.staging.did_produce(amount)
+    }
+}
+
+impl<T, A: Allocator> Drop for Reader<T, A> {
+    fn drop(&mut self) {
+        self.shared.lock().cursors[self.id] = None;
+        self.shared.writable.notify_one();
+    }
+}
@@ -0,0 +1,515 @@
+//! Streaming encoding and parsing of CBOR, as specified in RFC 8949.
+//!
+//! An `Encoder` writes data items piece by piece into a wrapped `BulkConsumer`, a `Parser` is a
+//! `Producer` of the `Event`s that describe the data items of a wrapped `BulkProducer`. Neither
+//! ever holds a whole data item: containers are delimited by events, and the contents of strings
+//! are passed on in chunks.
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use crate::*;
+use crate::utf8::Utf8Validator;
+
+const UINT: u8 = 0;
+const NEG_INT: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+// The additional information of indefinite-length items, and of the break stop code.
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = (SIMPLE << 5) | INDEFINITE;
+
+// The maximal length of the head of a data item.
+const MAX_HEAD_LEN: usize = 9;
+
+// Encodes the head of a data item into `out` in its shortest form, returns its length.
+fn encode_head(major: u8, argument: u64, out: &mut [u8; MAX_HEAD_LEN]) -> usize {
+    let major = major << 5;
+    let (additional, len) = match argument {
+        0..=23 => (argument as u8, 0),
+        24..=0xff => (24, 1),
+        0x100..=0xffff => (25, 2),
+        0x1_0000..=0xffff_ffff => (26, 4),
+        _ => (27, 8),
+    };
+    out[0] = major | additional;
+    out[1..1 + len].copy_from_slice(&argument.to_be_bytes()[8 - len..]);
+    1 + len
+}
+
+/// Creates an encoder that writes CBOR data items into `inner`.
+pub fn encoder<C>(inner: C) -> Encoder<C> {
+    Encoder { inner }
+}
+
+/// Writes CBOR data items into a wrapped `BulkConsumer`.
+///
+/// Each method writes a single data item, or the head of a container or string whose contents
+/// follow. Definite-length arrays and maps are followed by the given number of data items (twice
+/// as many for maps), indefinite-length ones by any number of items and an `end`. The encoder does
+/// not check that the written items are nested correctly.
+pub struct Encoder<C> {
+    inner: C,
+}
+
+impl<C: BulkConsumer<Item = u8>> Encoder<C> {
+    fn head(&mut self, major: u8, argument: u64) -> Result<(), C::Error> {
+        let mut out = [0; MAX_HEAD_LEN];
+        let len = encode_head(major, argument, &mut out);
+        consume_slice(&mut self.inner, &out[..len])
+    }
+
+    /// Writes an unsigned integer.
+    pub fn uint(&mut self, value: u64) -> Result<(), C::Error> {
+        self.head(UINT, value)
+    }
+
+    /// Writes the negative integer `-1 - value`, which covers the negative integers that do not
+    /// fit into an `i64`.
+    pub fn negative(&mut self, value: u64) -> Result<(), C::Error> {
+        self.head(NEG_INT, value)
+    }
+
+    /// Writes a signed integer.
+    pub fn int(&mut self, value: i64) -> Result<(), C::Error> {
+        if value < 0 {
+            self.negative(!(value as u64))
+        } else {
+            self.uint(value as u64)
+        }
+    }
+
+    /// Writes a definite-length byte string.
+    pub fn bytes(&mut self, bytes: &[u8]) -> Result<(), C::Error> {
+        self.bytes_start(Some(bytes.len() as u64))?;
+        self.raw(bytes)
+    }
+
+    /// Writes a definite-length text string.
+    pub fn text(&mut self, text: &str) -> Result<(), C::Error> {
+        self.text_start(Some(text.len() as u64))?;
+        self.raw(text.as_bytes())
+    }
+
+    /// Writes the head of a byte string. With a length, the contents must be written with `raw`
+    /// afterwards. Without, the contents must be written as definite-length byte strings, followed
+    /// by an `end`.
+    pub fn bytes_start(&mut self, len: Option<u64>) -> Result<(), C::Error> {
+        self.start(BYTES, len)
+    }
+
+    /// Writes the head of a text string. With a length, the contents must be written with `raw`
+    /// afterwards. Without, the contents must be written as definite-length text strings, followed
+    /// by an `end`.
+    pub fn text_start(&mut self, len: Option<u64>) -> Result<(), C::Error> {
+        self.start(TEXT, len)
+    }
+
+    /// Writes the head of an array of the given number of items, or of an indefinite-length array
+    /// which must be closed with an `end`.
+    pub fn array_start(&mut self, len: Option<u64>) -> Result<(), C::Error> {
+        self.start(ARRAY, len)
+    }
+
+    /// Writes the head of a map of the given number of key-value pairs, or of an
+    /// indefinite-length map which must be closed with an `end`.
+    pub fn map_start(&mut self, len: Option<u64>) -> Result<(), C::Error> {
+        self.start(MAP, len)
+    }
+
+    fn start(&mut self, major: u8, len: Option<u64>) -> Result<(), C::Error> {
+        match len {
+            Some(len) => self.head(major, len),
+            None => self.inner.consume((major << 5) | INDEFINITE),
+        }
+    }
+
+    /// Writes the break stop code, which ends the innermost indefinite-length item.
+    pub fn end(&mut self) -> Result<(), C::Error> {
+        self.inner.consume(BREAK)
+    }
+
+    /// Writes the contents of a definite-length string, in as many pieces as convenient.
+    pub fn raw(&mut self, contents: &[u8]) -> Result<(), C::Error> {
+        consume_slice(&mut self.inner, contents)
+    }
+
+    /// Writes a tag, which applies to the following data item.
+    pub fn tag(&mut self, tag: u64) -> Result<(), C::Error> {
+        self.head(TAG, tag)
+    }
+
+    /// Writes `false` or `true`.
+    pub fn bool(&mut self, value: bool) -> Result<(), C::Error> {
+        self.simple(if value { 21 } else { 20 })
+    }
+
+    /// Writes `null`.
+    pub fn null(&mut self) -> Result<(), C::Error> {
+        self.simple(22)
+    }
+
+    /// Writes `undefined`.
+    pub fn undefined(&mut self) -> Result<(), C::Error> {
+        self.simple(23)
+    }
+
+    /// Writes a simple value. The values 24 to 31 are not well-formed and must not be written.
+    pub fn simple(&mut self, value: u8) -> Result<(), C::Error> {
+        self.head(SIMPLE, value as u64)
+    }
+
+    /// Writes a single-precision float.
+    pub fn f32(&mut self, value: f32) -> Result<(), C::Error> {
+        self.inner.consume((SIMPLE << 5) | 26)?;
+        consume_slice(&mut self.inner, &value.to_bits().to_be_bytes())
+    }
+
+    /// Writes a double-precision float.
+    pub fn f64(&mut self, value: f64) -> Result<(), C::Error> {
+        self.inner.consume((SIMPLE << 5) | 27)?;
+        consume_slice(&mut self.inner, &value.to_bits().to_be_bytes())
+    }
+
+    /// Flushes the wrapped consumer.
+    pub fn flush(&mut self) -> Result<(), C::Error> {
+        self.inner.flush()
+    }
+}
+
+impl<C> Wrapper<C> for Encoder<C> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C> AsRef<C> for Encoder<C> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C> AsMut<C> for Encoder<C> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+/// A piece of a CBOR data item, as produced by a `Parser`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// An unsigned integer.
+    Uint(u64),
+    /// The negative integer `-1 - n`.
+    NegInt(u64),
+    /// The start of a byte string of the given length, or of an indefinite-length one. The
+    /// contents follow as `Bytes` chunks, an indefinite-length string is closed by a `Break`.
+    BytesStart(Option<u64>),
+    /// A chunk of the contents of a byte string, of the given length, available from
+    /// `Parser::chunk`.
+    Bytes(usize),
+    /// The start of a text string of the given length in bytes, or of an indefinite-length one.
+    /// The contents follow as `Text` chunks, an indefinite-length string is closed by a `Break`.
+    TextStart(Option<u64>),
+    /// A chunk of the contents of a text string, of the given length, available from
+    /// `Parser::chunk`. A chunk may end within a code point, only the whole string is guaranteed
+    /// to be valid UTF-8.
+    Text(usize),
+    /// The start of an array of the given number of items, or of an indefinite-length one which
+    /// is closed by a `Break`.
+    ArrayStart(Option<u64>),
+    /// The start of a map of the given number of key-value pairs, or of an indefinite-length one
+    /// which is closed by a `Break`.
+    MapStart(Option<u64>),
+    /// A tag, which applies to the following data item.
+    Tag(u64),
+    /// `false` or `true`.
+    Bool(bool),
+    /// `null`
+    Null,
+    /// `undefined`
+    Undefined,
+    /// Any other simple value.
+    Simple(u8),
+    /// A half-, single- or double-precision float.
+    Float(f64),
+    /// The end of an indefinite-length item.
+    Break,
+}
+
+/// Emitted by a `Parser`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseError<E> {
+    /// The wrapped producer emitted an error between two data items, e.g. because the input has
+    /// ended.
+    Inner(E),
+    /// The given initial byte, or the second byte of a two-byte simple value, is not well-formed.
+    InvalidHead(u8),
+    /// An indefinite-length string contains a data item with the given initial byte that is not a
+    /// definite-length string of the same type.
+    InvalidSegment(u8),
+    /// A text string is not valid UTF-8.
+    InvalidUtf8,
+    /// The input ended within a data item.
+    Incomplete,
+}
+
+// Converts a half-precision float to a double-precision one.
+fn f16_to_f64(half: u16) -> f64 {
+    let sign = ((half >> 15) as u64) << 63;
+    let exponent = ((half >> 10) & 0x1f) as u64;
+    let mantissa = (half & 0x3ff) as u64;
+
+    let magnitude = match exponent {
+        // subnormal, `mantissa * 2^-24`
+        0 => mantissa as f64 / 16_777_216.0,
+        // infinity or NaN
+        0x1f => f64::from_bits((0x7ff << 52) | (mantissa << 42)),
+        _ => f64::from_bits(((exponent + 1023 - 15) << 52) | (mantissa << 42)),
+    };
+    f64::from_bits(sign | magnitude.to_bits())
+}
+
+// The number of bytes following the given initial byte in the head of a data item.
+fn argument_len(initial: u8) -> usize {
+    match initial & 0x1f {
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => 0,
+    }
+}
+
+// The string that is being parsed.
+#[derive(Clone, Copy)]
+struct StringState {
+    text: bool,
+    indefinite: bool,
+    // the number of bytes of the current definite-length string or segment that have not been
+    // read yet
+    remaining: u64,
+}
+
+struct ParseState {
+    head: [u8; MAX_HEAD_LEN],
+    head_len: usize,
+    string: Option<StringState>,
+    utf8: Utf8Validator,
+    // the length of the current chunk in the buffer
+    chunk_len: usize,
+}
+
+impl ParseState {
+    // Turns a complete head into an event. Returns `None` for heads that do not correspond to an
+    // event, i.e. those of the segments of indefinite-length strings.
+    fn decode_head<E>(&mut self) -> Result<Option<Event>, ParseError<E>> {
+        let initial = self.head[0];
+        let major = initial >> 5;
+        let additional = initial & 0x1f;
+        let mut argument_bytes = [0; 8];
+        let len = argument_len(initial);
+        argument_bytes[8 - len..].copy_from_slice(&self.head[1..1 + len]);
+        let argument = if additional < 24 { additional as u64 } else { u64::from_be_bytes(argument_bytes) };
+        let indefinite = additional == INDEFINITE;
+
+        if (28..31).contains(&additional) {
+            return Err(ParseError::InvalidHead(initial));
+        }
+
+        if let Some(string) = self.string.as_mut() {
+            // Within an indefinite-length string, between segments.
+            if initial == BREAK {
+                self.string = None;
+                return Ok(Some(Event::Break));
+            }
+            let expected = if string.text { TEXT } else { BYTES };
+            if major != expected || indefinite {
+                return Err(ParseError::InvalidSegment(initial));
+            }
+            string.remaining = argument;
+            return Ok(None);
+        }
+
+        let len = if indefinite { None } else { Some(argument) };
+        Ok(Some(match major {
+            UINT | NEG_INT | TAG if indefinite => return Err(ParseError::InvalidHead(initial)),
+            UINT => Event::Uint(argument),
+            NEG_INT => Event::NegInt(argument),
+            BYTES | TEXT => {
+                let text = major == TEXT;
+                if indefinite || argument > 0 {
+                    self.string = Some(StringState { text, indefinite, remaining: if indefinite { 0 } else { argument } });
+                    self.utf8 = Utf8Validator::new();
+                }
+                if text { Event::TextStart(len) } else { Event::BytesStart(len) }
+            }
+            ARRAY => Event::ArrayStart(len),
+            MAP => Event::MapStart(len),
+            TAG => Event::Tag(argument),
+            _ => match additional {
+                20 => Event::Bool(false),
+                21 => Event::Bool(true),
+                22 => Event::Null,
+                23 => Event::Undefined,
+                24 if argument < 32 => return Err(ParseError::InvalidHead(argument as u8)),
+                0..=24 => Event::Simple(argument as u8),
+                25 => Event::Float(f16_to_f64(argument as u16)),
+                26 => Event::Float(f32::from_bits(argument as u32) as f64),
+                27 => Event::Float(f64::from_bits(argument)),
+                _ => Event::Break,
+            },
+        }))
+    }
+}
+
+/// Creates a parser that produces the events of the CBOR data items that `inner` produces,
+/// copying the chunks of strings into `buffer`. Panics if the buffer is empty.
+pub fn parser<P, B: AsMut<[u8]>>(inner: P, mut buffer: B) -> Parser<P, B> {
+    assert!(!buffer.as_mut().is_empty(), "a CBOR parser needs a nonempty buffer");
+    Parser {
+        inner,
+        buffer,
+        state: ParseState {
+            head: [0; MAX_HEAD_LEN],
+            head_len: 0,
+            string: None,
+            utf8: Utf8Validator::new(),
+            chunk_len: 0,
+        },
+    }
+}
+
+/// Produces the `Event`s of the CBOR data items of a wrapped `BulkProducer`, e.g. of a CBOR
+/// sequence.
+///
+/// The contents of strings are produced as chunks, each of which is taken from a single call to
+/// `producer_slots` of the wrapped producer and copied into the buffer of the parser. The buffer
+/// bounds the chunk length, it can hold any type that can be viewed as a mutable slice of bytes.
+/// The chunk of a `Bytes` or `Text` event is available from `chunk` until the next call to
+/// `produce`.
+///
+/// The parser checks that each data item is well-formed and that text strings are valid UTF-8,
+/// but not that containers are nested correctly, e.g. a `Break` outside of indefinite-length
+/// items is produced as is.
+pub struct Parser<P, B> {
+    inner: P,
+    buffer: B,
+    state: ParseState,
+}
+
+impl<P, B: AsRef<[u8]>> Parser<P, B> {
+    /// Returns the chunk of the most recently produced `Bytes` or `Text` event, or an empty slice
+    /// after any other event.
+    pub fn chunk(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.state.chunk_len]
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]>> Parser<P, B> {
+    // Reads the next chunk of the current string.
+    fn read_chunk(&mut self, string: StringState) -> Result<Event, ParseError<P::Error>> {
+        let bytes = self.inner.producer_slots().map_err(|_| ParseError::Incomplete)?;
+        let buffer = self.buffer.as_mut();
+        let amount = min(min(bytes.len_(), buffer.len()) as u64, string.remaining) as usize;
+        buffer[..amount].copy_from_slice(&bytes[..amount]);
+        self.inner.did_produce(unsafe { NonZeroUsize::new_unchecked(amount) });
+        self.state.chunk_len = amount;
+
+        let remaining = string.remaining - amount as u64;
+        if string.text {
+            // Each segment of an indefinite-length string must be valid UTF-8 on its own.
+            if !self.state.utf8.push_slice(&buffer[..amount]) || (remaining == 0 && !self.state.utf8.is_complete()) {
+                self.state.string = None;
+                return Err(ParseError::InvalidUtf8);
+            }
+        }
+
+        self.state.string = if remaining == 0 && !string.indefinite {
+            None
+        } else {
+            Some(StringState { remaining, ..string })
+        };
+        Ok(if string.text { Event::Text(amount) } else { Event::Bytes(amount) })
+    }
+
+    // Reads the head of the next data item.
+    fn read_head(&mut self) -> Result<(), ParseError<P::Error>> {
+        loop {
+            let needed = if self.state.head_len == 0 { 1 } else { 1 + argument_len(self.state.head[0]) };
+            if self.state.head_len == needed {
+                self.state.head_len = 0;
+                return Ok(());
+            }
+
+            let bytes = match self.inner.producer_slots() {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return Err(if self.state.head_len == 0 && self.state.string.is_none() {
+                        ParseError::Inner(e)
+                    } else {
+                        ParseError::Incomplete
+                    });
+                }
+            };
+            let amount = min(bytes.len_(), needed - self.state.head_len);
+            self.state.head[self.state.head_len..self.state.head_len + amount].copy_from_slice(&bytes[..amount]);
+            self.state.head_len += amount;
+            self.inner.did_produce(unsafe { NonZeroUsize::new_unchecked(amount) });
+        }
+    }
+}
+
+impl<P, B> Wrapper<P> for Parser<P, B> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P, B> AsRef<P> for Parser<P, B> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, B> AsMut<P> for Parser<P, B> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]>> Producer for Parser<P, B> {
+    type Item = Event;
+    type Error = ParseError<P::Error>;
+
+    fn produce(&mut self) -> Result<Event, Self::Error> {
+        self.state.chunk_len = 0;
+        loop {
+            if let Some(string) = self.state.string {
+                if string.remaining > 0 {
+                    return self.read_chunk(string);
+                }
+            }
+
+            self.read_head()?;
+            if let Some(event) = self.state.decode_head()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(ParseError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.remaining_hint().1)
+    }
+}
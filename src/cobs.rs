@@ -0,0 +1,301 @@
+//! Framing with Consistent Overhead Byte Stuffing (COBS).
+//!
+//! COBS encodes a frame of arbitrary bytes without using the byte zero, so that frames can be
+//! delimited by zeros. An `Encoder` is a `Consumer` of frames that writes their zero-terminated
+//! encodings into a wrapped `BulkConsumer`, a `Decoder` is a `Producer` of the frames whose
+//! encodings a wrapped `BulkProducer` produces.
+//!
+//! Neither needs an allocator: the encoder writes directly from the consumed frames, and the
+//! decoder decodes into a buffer provided by the caller.
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::marker::PhantomData;
+
+use wrapper::Wrapper;
+
+use crate::*;
+use crate::frame::{produce_frame, FrameState};
+
+// The largest code byte, which is followed by 254 nonzero bytes and no implied zero.
+const MAX_CODE: u8 = 0xff;
+
+/// Creates an encoder that writes the COBS encodings of all consumed frames into `inner`.
+pub fn encoder<C, F>(inner: C) -> Encoder<C, F> {
+    Encoder {
+        inner,
+        frames: PhantomData,
+    }
+}
+
+/// Consumes frames, i.e. anything that can be viewed as a slice of bytes, and writes their COBS
+/// encodings into a wrapped `BulkConsumer`, each followed by a zero.
+pub struct Encoder<C, F> {
+    inner: C,
+    frames: PhantomData<fn(F)>,
+}
+
+impl<C, F> Wrapper<C> for Encoder<C, F> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C, F> AsRef<C> for Encoder<C, F> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C, F> AsMut<C> for Encoder<C, F> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: BulkConsumer<Item = u8>, F: AsRef<[u8]>> Consumer for Encoder<C, F> {
+    type Item = F;
+    type Error = C::Error;
+
+    fn consume(&mut self, item: F) -> Result<(), Self::Error> {
+        let mut frame = item.as_ref();
+        loop {
+            // Each block consists of a code byte and up to 254 nonzero bytes. Unless the code is
+            // the maximal one, the block stands for its bytes followed by a zero.
+            let len = min(frame.len(), MAX_CODE as usize - 1);
+            let block = match frame[..len].iter().position(|b| *b == 0) {
+                Some(zero) => zero,
+                None => len,
+            };
+            let code = block as u8 + 1;
+
+            self.inner.consume(code)?;
+            consume_slice(&mut self.inner, &frame[..block])?;
+
+            if code == MAX_CODE {
+                frame = &frame[block..];
+                if frame.is_empty() {
+                    // The frame ended with a maximal block, so there is no implied zero to drop.
+                    break;
+                }
+            } else if block < frame.len() {
+                // Skip the zero that the block stands for.
+                frame = &frame[block + 1..];
+            } else {
+                // The implied zero of the last block is not part of the frame.
+                break;
+            }
+        }
+
+        self.inner.consume(0)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Emitted by a `Decoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError<E> {
+    /// The wrapped producer emitted an error, e.g. because the input has ended. All frames of
+    /// the input have been produced before.
+    Inner(E),
+    /// A frame ended within a block, so it has been corrupted. The decoder continues with the
+    /// next frame.
+    Corrupt,
+    /// A decoded frame did not fit into the buffer of the decoder. The decoder skips the rest of
+    /// the frame and continues with the next one.
+    TooLong,
+    /// The input ended within a frame.
+    Incomplete,
+}
+
+// The progress of decoding a single frame.
+struct DecodeState {
+    // the number of decoded bytes in the buffer
+    len: usize,
+    // whether any byte of the current frame has been read
+    in_frame: bool,
+    // the number of bytes of the current block that have not been read yet
+    remaining: u8,
+    // whether the current block is a maximal one, which has no implied zero
+    maximal: bool,
+    // whether the rest of the current frame is skipped, up to the next zero
+    skip: bool,
+}
+
+impl DecodeState {
+    fn reset(&mut self) {
+        self.in_frame = false;
+        self.remaining = 0;
+        self.maximal = false;
+    }
+
+    // Appends a decoded byte to the buffer.
+    fn push<E>(&mut self, buffer: &mut [u8], byte: u8) -> Result<(), DecodeError<E>> {
+        if self.len == buffer.len() {
+            self.reset();
+            self.len = 0;
+            self.skip = true;
+            Err(DecodeError::TooLong)
+        } else {
+            buffer[self.len] = byte;
+            self.len += 1;
+            Ok(())
+        }
+    }
+}
+
+impl<E> FrameState<E> for DecodeState {
+    type Error = DecodeError<E>;
+
+    fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn decode(&mut self, buffer: &mut [u8], bytes: &[u8]) -> (usize, Option<Result<(), DecodeError<E>>>) {
+        let mut read = 0;
+        while read < bytes.len() {
+            let byte = bytes[read];
+
+            if self.skip {
+                read += 1;
+                if byte == 0 {
+                    self.skip = false;
+                }
+            } else if byte == 0 {
+                read += 1;
+                if !self.in_frame {
+                    // Empty frames are ignored, so that zeros can be sent to synchronize.
+                    continue;
+                } else if self.remaining > 0 {
+                    self.reset();
+                    self.len = 0;
+                    return (read, Some(Err(DecodeError::Corrupt)));
+                } else {
+                    self.reset();
+                    return (read, Some(Ok(())));
+                }
+            } else if self.remaining == 0 {
+                read += 1;
+                // A new block, so the previous one stood for a zero unless it was maximal.
+                if self.in_frame && !self.maximal {
+                    if let Err(e) = self.push(buffer, 0) {
+                        return (read, Some(Err(e)));
+                    }
+                }
+                self.in_frame = true;
+                self.maximal = byte == MAX_CODE;
+                self.remaining = byte - 1;
+            } else {
+                // Copy as much of the block as is available, up to any premature zero.
+                let available = &bytes[read..min(bytes.len(), read + self.remaining as usize)];
+                let nonzero = match available.iter().position(|b| *b == 0) {
+                    Some(zero) => zero,
+                    None => available.len(),
+                };
+                let amount = min(nonzero, buffer.len() - self.len);
+                buffer[self.len..self.len + amount].copy_from_slice(&available[..amount]);
+                self.len += amount;
+                self.remaining -= amount as u8;
+                read += amount;
+
+                if amount < nonzero {
+                    if let Err(e) = self.push(buffer, available[amount]) {
+                        return (read, Some(Err(e)));
+                    }
+                }
+            }
+        }
+        (read, None)
+    }
+
+    fn end(&mut self, e: E) -> DecodeError<E> {
+        if self.in_frame {
+            self.reset();
+            self.len = 0;
+            DecodeError::Incomplete
+        } else {
+            DecodeError::Inner(e)
+        }
+    }
+}
+
+/// Creates a decoder that produces the frames whose COBS encodings `inner` produces, decoding
+/// each of them into `buffer`.
+pub fn decoder<P, B>(inner: P, buffer: B) -> Decoder<P, B> {
+    Decoder {
+        inner,
+        buffer,
+        state: DecodeState {
+            len: 0,
+            in_frame: false,
+            remaining: 0,
+            maximal: false,
+            skip: false,
+        },
+    }
+}
+
+/// Produces the frames encoded by the zero-delimited COBS encodings of a wrapped `BulkProducer`.
+///
+/// Each frame is decoded into the buffer of the decoder, which can hold any type that can be
+/// viewed as a mutable slice of bytes, e.g. an array or a `&mut [u8]`. The decoder produces the
+/// length of each frame, the frame itself is available from `frame` until the next call to
+/// `produce`. Empty encodings, i.e. consecutive zeros, are skipped.
+pub struct Decoder<P, B> {
+    inner: P,
+    buffer: B,
+    state: DecodeState,
+}
+
+impl<P, B: AsRef<[u8]>> Decoder<P, B> {
+    /// Returns the most recently produced frame.
+    pub fn frame(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.state.len]
+    }
+}
+
+impl<P, B> Wrapper<P> for Decoder<P, B> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P, B> AsRef<P> for Decoder<P, B> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, B> AsMut<P> for Decoder<P, B> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]>> Producer for Decoder<P, B> {
+    /// The length of the produced frame.
+    type Item = usize;
+    type Error = DecodeError<P::Error>;
+
+    fn produce(&mut self) -> Result<usize, Self::Error> {
+        produce_frame(&mut self.inner, &mut self.state, self.buffer.as_mut())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(DecodeError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.remaining_hint().1)
+    }
+}
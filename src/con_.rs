@@ -4,17 +4,46 @@ use core::cmp::min;
 
 use slice_n::Slice1;
 
+use crate::write_slice;
+
 mod cursor;
 pub use cursor::*;
 
+mod owned_cursor;
+pub use owned_cursor::*;
+
 mod map_err;
 pub use map_err::*;
 
-#[cfg(all(feature = "alloc", feature = "arbitrary"))]
+mod faulty;
+pub use faulty::*;
+
+mod timeout;
+pub use timeout::*;
+
+mod extend;
+pub use extend::*;
+
+#[cfg(feature = "alloc")]
+mod into_vec;
+#[cfg(feature = "alloc")]
+pub use into_vec::*;
+
+#[cfg(feature = "alloc")]
+mod collections;
+#[cfg(feature = "alloc")]
+pub use collections::*;
+
+#[cfg(feature = "alloc")]
 mod scramble;
-#[cfg(all(feature = "alloc", feature = "arbitrary"))]
+#[cfg(feature = "alloc")]
 pub use scramble::*;
 
+#[cfg(feature = "alloc")]
+mod record;
+#[cfg(feature = "alloc")]
+pub use record::*;
+
 /// A `Consumer` consumes items one by one.
 pub trait Consumer {
     /// The type of values that are consumed by the `Consumer`.
@@ -29,6 +58,19 @@ pub trait Consumer {
     /// A `Consumer` is allowed to store written data in a buffer without immediately processing
     /// it. This method triggers immediate processing of all currently buffered data.
     fn flush(&mut self) -> Result<(), Self::Error>;
+
+    /// Returns bounds on the number of items the `Consumer` can consume before it emits an error,
+    /// in the same way as `Iterator::size_hint`. The lower bound must not exceed the true number,
+    /// an upper bound of `None` means no upper bound is known. The default implementation returns
+    /// `(0, None)`, which is correct for any `Consumer`.
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+
+    /// Informs the `Consumer` that at least `additional` further items are about to be consumed,
+    /// so that it can prepare for them, e.g. by allocating memory up front. The default
+    /// implementation does nothing.
+    fn reserve(&mut self, _additional: usize) {}
 }
 
 /// A `BulkConsumer` can consume multiple pieces of copyable data at a time.
@@ -42,16 +84,38 @@ pub trait BulkConsumer: Consumer where Self::Item: Copy {
     /// initialized.
     unsafe fn did_consume(&mut self, amount: NonZeroUsize);
 
+    /// Returns two buffers into which items can be placed, the slots of the second one following
+    /// those of the first. Only the first one is guaranteed to be nonempty. This allows e.g. ring
+    /// buffers to expose all of their free space even when it wraps around. A subsequent
+    /// `did_consume` may report more items than fit into the first buffer, but no more than fit
+    /// into both buffers combined, and the first buffer must be filled completely before any
+    /// items are placed in the second one. The memory in the buffers is not necessarily
+    /// initialized.
+    ///
+    /// The default implementation returns `consumer_slots` and an empty second buffer.
+    fn consumer_slots_vectored(&mut self) -> Result<(&mut Slice1<MaybeUninit<Self::Item>>, &mut [MaybeUninit<Self::Item>]), Self::Error> {
+        Ok((self.consumer_slots()?, &mut []))
+    }
+
     /// The `BulkConsumer` consumes a non-zero number of items from the provided buffer, and
     /// returns how many it has consumed.
     fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
         let l = self.consumer_slots()?;
         let amount = min(l.len_(), data.len_());
-        MaybeUninit::write_slice(&mut l[..amount], &data[..amount]);
+        write_slice(&mut l[..amount], &data[..amount]);
         unsafe {
             let amount = NonZeroUsize::new_unchecked(amount);
             self.did_consume(amount);
             Ok(amount)
         }
     }
+
+    /// The `BulkConsumer` consumes a non-zero number of items from the provided buffers, taking
+    /// items from the second one only after all items of the first one, and returns how many it
+    /// has consumed. This corresponds to a `writev` call.
+    ///
+    /// The default implementation only consumes from the first buffer.
+    fn bulk_consume_vectored(&mut self, fst: &Slice1<Self::Item>, _snd: &[Self::Item]) -> Result<NonZeroUsize, Self::Error> {
+        self.bulk_consume(fst)
+    }
 }
@@ -0,0 +1,405 @@
+extern crate maybe_std as base;
+
+use base::collections::VecDeque;
+use base::string::String;
+use base::vec::Vec;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+use core::{ptr, slice, str};
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::allocator::*;
+use crate::con::{Consumer, BulkConsumer};
+use crate::Infallible;
+use crate::slice_assume_init_ref;
+
+/// Creates a consumer which appends consumed data to the given `Vec`.
+#[cfg(feature = "unstable")]
+pub fn append_vec<'a, T, A: Allocator>(v: &'a mut Vec<T, A>) -> AppendVec<'a, T, A> {
+    AppendVec(v)
+}
+
+/// Creates a consumer which appends consumed data to the given `Vec`.
+#[cfg(not(feature = "unstable"))]
+pub fn append_vec<'a, T>(v: &'a mut Vec<T>) -> AppendVec<'a, T> {
+    AppendVec(in_global_mut(v))
+}
+
+/// Consumes data by appending it to a `Vec`. Bulk consumption writes directly into the spare
+/// capacity of the `Vec`.
+pub struct AppendVec<'a, T, A: Allocator = Global>(&'a mut VecIn<T, A>);
+
+#[cfg(feature = "unstable")]
+impl<'a, T, A: Allocator> Wrapper<&'a mut Vec<T, A>> for AppendVec<'a, T, A> {
+    fn into_inner(self) -> &'a mut Vec<T, A> {
+        self.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<'a, T, A: Allocator> AsRef<Vec<T, A>> for AppendVec<'a, T, A> {
+    fn as_ref(&self) -> &Vec<T, A> {
+        self.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<'a, T, A: Allocator> AsMut<Vec<T, A>> for AppendVec<'a, T, A> {
+    fn as_mut(&mut self) -> &mut Vec<T, A> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T> Wrapper<&'a mut Vec<T>> for AppendVec<'a, T> {
+    fn into_inner(self) -> &'a mut Vec<T> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T> AsRef<Vec<T>> for AppendVec<'a, T> {
+    fn as_ref(&self) -> &Vec<T> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T> AsMut<Vec<T>> for AppendVec<'a, T> {
+    fn as_mut(&mut self) -> &mut Vec<T> {
+        self.0
+    }
+}
+
+impl<'a, T, A: Allocator> Consumer for AppendVec<'a, T, A> {
+    type Item = T;
+    type Error = Infallible;
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        Ok(self.0.push(item))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
+}
+
+impl<'a, T: Copy, A: Allocator> BulkConsumer for AppendVec<'a, T, A> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        if self.0.capacity() == self.0.len() {
+            self.0.reserve(1);
+        }
+
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(self.0.spare_capacity_mut()) })
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        let len = self.0.len();
+        self.0.set_len(len + amount.get());
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        self.0.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn bulk_consume_vectored(&mut self, fst: &Slice1<Self::Item>, snd: &[Self::Item]) -> Result<NonZeroUsize, Self::Error> {
+        self.0.reserve(fst.len_() + snd.len());
+        self.0.extend_from_slice(fst);
+        self.0.extend_from_slice(snd);
+        Ok(unsafe { NonZeroUsize::new_unchecked(fst.len_() + snd.len()) })
+    }
+}
+
+/// Creates a consumer which appends consumed data to the back of the given `VecDeque`.
+#[cfg(feature = "unstable")]
+pub fn append_vec_deque<'a, T, A: Allocator>(v: &'a mut VecDeque<T, A>) -> AppendVecDeque<'a, T, A> {
+    AppendVecDeque(v)
+}
+
+/// Creates a consumer which appends consumed data to the back of the given `VecDeque`.
+#[cfg(not(feature = "unstable"))]
+pub fn append_vec_deque<'a, T>(v: &'a mut VecDeque<T>) -> AppendVecDeque<'a, T> {
+    AppendVecDeque(in_global_mut(v))
+}
+
+/// Consumes data by pushing it to the back of a `VecDeque`. Bulk consumption writes directly into
+/// the spare capacity of the `VecDeque`, after moving its items to the start of its buffer.
+pub struct AppendVecDeque<'a, T, A: Allocator = Global>(&'a mut VecDequeIn<T, A>);
+
+#[cfg(feature = "unstable")]
+impl<'a, T, A: Allocator> Wrapper<&'a mut VecDeque<T, A>> for AppendVecDeque<'a, T, A> {
+    fn into_inner(self) -> &'a mut VecDeque<T, A> {
+        self.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<'a, T, A: Allocator> AsRef<VecDeque<T, A>> for AppendVecDeque<'a, T, A> {
+    fn as_ref(&self) -> &VecDeque<T, A> {
+        self.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<'a, T, A: Allocator> AsMut<VecDeque<T, A>> for AppendVecDeque<'a, T, A> {
+    fn as_mut(&mut self) -> &mut VecDeque<T, A> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T> Wrapper<&'a mut VecDeque<T>> for AppendVecDeque<'a, T> {
+    fn into_inner(self) -> &'a mut VecDeque<T> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T> AsRef<VecDeque<T>> for AppendVecDeque<'a, T> {
+    fn as_ref(&self) -> &VecDeque<T> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<'a, T> AsMut<VecDeque<T>> for AppendVecDeque<'a, T> {
+    fn as_mut(&mut self) -> &mut VecDeque<T> {
+        self.0
+    }
+}
+
+impl<'a, T, A: Allocator> Consumer for AppendVecDeque<'a, T, A> {
+    type Item = T;
+    type Error = Infallible;
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        Ok(self.0.push_back(item))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
+}
+
+impl<'a, T: Copy, A: Allocator> BulkConsumer for AppendVecDeque<'a, T, A> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        if self.0.capacity() == self.0.len() {
+            self.0.reserve(1);
+        }
+
+        let (ptr, len) = unsafe {
+            with_vec(&mut *self.0, |vec| {
+                let spare = vec.spare_capacity_mut();
+                (spare.as_mut_ptr(), spare.len())
+            })
+        };
+        // The deque keeps the buffer of the `Vec`, so its spare capacity is the same memory.
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(slice::from_raw_parts_mut(ptr, len)) })
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        with_vec(&mut *self.0, |vec| {
+            let len = vec.len();
+            vec.set_len(len + amount.get());
+        })
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        self.0.extend(data.iter());
+        Ok(data.len())
+    }
+
+    fn bulk_consume_vectored(&mut self, fst: &Slice1<Self::Item>, snd: &[Self::Item]) -> Result<NonZeroUsize, Self::Error> {
+        self.0.extend(fst.iter().chain(snd));
+        Ok(unsafe { NonZeroUsize::new_unchecked(fst.len_() + snd.len()) })
+    }
+}
+
+// Runs `f` on the items of the deque as a `Vec`, which takes over the buffer of the deque with the
+// first item at its start. This moves the items if they wrap around the end of the buffer. Turning
+// the `Vec` back into a deque keeps the buffer and the positions of the items. `f` must not panic.
+#[cfg(feature = "unstable")]
+unsafe fn with_vec<T, A: Allocator, R>(deque: &mut VecDeque<T, A>, f: impl FnOnce(&mut Vec<T, A>) -> R) -> R {
+    let mut vec = Vec::from(ptr::read(deque));
+    let result = f(&mut vec);
+    ptr::write(deque, VecDeque::from(vec));
+    result
+}
+
+// Runs `f` on the items of the deque as a `Vec`, which takes over the buffer of the deque with the
+// first item at its start. This moves the items if they wrap around the end of the buffer. Turning
+// the `Vec` back into a deque keeps the buffer and the positions of the items. `f` must not panic.
+#[cfg(not(feature = "unstable"))]
+unsafe fn with_vec<T, R>(deque: &mut VecDeque<T>, f: impl FnOnce(&mut Vec<T>) -> R) -> R {
+    let mut vec = Vec::from(ptr::read(deque));
+    let result = f(&mut vec);
+    ptr::write(deque, VecDeque::from(vec));
+    result
+}
+
+/// Emitted by an `AppendString` when the consumed bytes are not valid UTF-8, or when it is flushed
+/// while the encoding of a code point has only been partially consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidUtf8;
+
+/// Creates a consumer which appends consumed bytes to the given `String`, validating that they
+/// form UTF-8.
+pub fn append_string<'a>(s: &'a mut String) -> AppendString<'a> {
+    AppendString {
+        string: s,
+        pending: 0,
+        invalid: false,
+    }
+}
+
+/// Consumes bytes by appending them to a `String`. A code point whose encoding has only been
+/// partially consumed is held back until it is complete. Bulk consumption writes directly into
+/// the spare capacity of the `String`.
+pub struct AppendString<'a> {
+    string: &'a mut String,
+    // number of bytes of an incomplete code point, stored in the spare capacity of the `String`
+    pending: usize,
+    // whether invalid UTF-8 was placed into the consumer via `did_consume`
+    invalid: bool,
+}
+
+impl<'a> AppendString<'a> {
+    /// Returns how many bytes of an incomplete code point have been consumed but not yet been
+    /// appended to the `String`.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    fn vec(&mut self) -> &mut Vec<u8> {
+        unsafe { self.string.as_mut_vec() }
+    }
+
+    // Ensures that the spare capacity has room beyond the pending bytes, which are preserved.
+    fn reserve_beyond_pending(&mut self, additional: usize) {
+        let pending = self.pending;
+        let mut tmp = [0; 4];
+        let vec = self.vec();
+        if vec.capacity() - vec.len() >= pending.saturating_add(additional) {
+            return;
+        }
+
+        for (i, byte) in vec.spare_capacity_mut()[..pending].iter().enumerate() {
+            tmp[i] = unsafe { byte.assume_init() };
+        }
+        vec.reserve(pending.saturating_add(additional));
+        for (i, byte) in vec.spare_capacity_mut()[..pending].iter_mut().enumerate() {
+            byte.write(tmp[i]);
+        }
+    }
+
+    // Validates the `amount` bytes following the pending ones, and moves everything that forms
+    // complete code points into the `String`.
+    unsafe fn validate(&mut self, amount: usize) {
+        let pending = self.pending;
+        let vec = self.vec();
+        let len = vec.len();
+        let total = pending + amount;
+        let new = slice_assume_init_ref(&vec.spare_capacity_mut()[..total]);
+
+        match str::from_utf8(new) {
+            Ok(_) => {
+                vec.set_len(len + total);
+                self.pending = 0;
+            }
+            Err(e) => {
+                let valid = e.valid_up_to();
+                vec.set_len(len + valid);
+                self.pending = total - valid;
+                if e.error_len().is_some() {
+                    self.invalid = true;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Wrapper<&'a mut String> for AppendString<'a> {
+    /// Discards the bytes of an incompletely consumed code point, see `pending`.
+    fn into_inner(self) -> &'a mut String {
+        self.string
+    }
+}
+
+impl<'a> AsRef<String> for AppendString<'a> {
+    fn as_ref(&self) -> &String {
+        self.string
+    }
+}
+
+impl<'a> Consumer for AppendString<'a> {
+    type Item = u8;
+    /// Emitted when the consumed bytes are not valid UTF-8.
+    type Error = InvalidUtf8;
+
+    fn consume(&mut self, item: u8) -> Result<(), Self::Error> {
+        if self.invalid {
+            return Err(InvalidUtf8);
+        }
+
+        self.reserve_beyond_pending(1);
+        let pending = self.pending;
+        self.vec().spare_capacity_mut()[pending].write(item);
+        unsafe { self.validate(1) };
+
+        if self.invalid {
+            Err(InvalidUtf8)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fails if the encoding of a code point has only been partially consumed, as the input would
+    /// be truncated otherwise.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.invalid || self.pending > 0 {
+            Err(InvalidUtf8)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.reserve_beyond_pending(additional)
+    }
+}
+
+impl<'a> BulkConsumer for AppendString<'a> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        if self.invalid {
+            return Err(InvalidUtf8);
+        }
+
+        self.reserve_beyond_pending(1);
+        let pending = self.pending;
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(&mut self.vec().spare_capacity_mut()[pending..]) })
+    }
+
+    /// Invalid UTF-8 is reported by the next method call that can fail.
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.validate(amount.get())
+    }
+}
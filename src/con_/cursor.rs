@@ -1,3 +1,4 @@
+use core::cmp::min;
 use core::convert::{AsRef, AsMut};
 use core::num::NonZeroUsize;
 use core::mem::MaybeUninit;
@@ -7,6 +8,7 @@ use wrapper::Wrapper;
 
 use crate::con::{Consumer, BulkConsumer};
 use crate::maybe_uninit_slice_mut;
+use crate::write_slice;
 
 /// Creates a consumes which places consumed data in the given slice.
 pub fn cursor<'a, T>(s: &'a mut [T]) -> Cursor<'a, T> {
@@ -52,6 +54,11 @@ impl<'a, T> Consumer for Cursor<'a, T> {
     fn flush(&mut self) -> Result<(), Self::Error> {
         Ok(())
     }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.0.len() - self.1;
+        (remaining, Some(remaining))
+    }
 }
 
 impl<'a, T: Copy> BulkConsumer for Cursor<'a, T> {
@@ -66,4 +73,17 @@ impl<'a, T: Copy> BulkConsumer for Cursor<'a, T> {
     unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
         self.1 += amount.get();
     }
+
+    fn bulk_consume_vectored(&mut self, fst: &Slice1<Self::Item>, snd: &[Self::Item]) -> Result<NonZeroUsize, Self::Error> {
+        let l = self.consumer_slots()?;
+        let fst_amount = min(l.len_(), fst.len_());
+        write_slice(&mut l[..fst_amount], &fst[..fst_amount]);
+        let snd_amount = min(l.len_() - fst_amount, snd.len());
+        write_slice(&mut l[fst_amount..fst_amount + snd_amount], &snd[..snd_amount]);
+        unsafe {
+            let amount = NonZeroUsize::new_unchecked(fst_amount + snd_amount);
+            self.did_consume(amount);
+            Ok(amount)
+        }
+    }
 }
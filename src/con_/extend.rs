@@ -0,0 +1,51 @@
+use core::iter::{Extend, once};
+use core::marker::PhantomData;
+
+use wrapper::Wrapper;
+
+use crate::con::Consumer;
+use crate::Infallible;
+
+/// Creates a consumer which places consumed data in the given `Extend`, for example a collection.
+pub fn from_extend<'a, X: Extend<T>, T>(x: &'a mut X) -> FromExtend<'a, X, T> {
+    FromExtend(x, PhantomData)
+}
+
+/// Consumes data into an `Extend`.
+pub struct FromExtend<'a, X, T>(&'a mut X, PhantomData<fn(T)>);
+
+impl<'a, X, T> Wrapper<&'a mut X> for FromExtend<'a, X, T> {
+    fn into_inner(self) -> &'a mut X {
+        self.0
+    }
+}
+
+impl<'a, X, T> AsRef<X> for FromExtend<'a, X, T> {
+    fn as_ref(&self) -> &X {
+        self.0
+    }
+}
+
+impl<'a, X, T> AsMut<X> for FromExtend<'a, X, T> {
+    fn as_mut(&mut self) -> &mut X {
+        self.0
+    }
+}
+
+impl<'a, X: Extend<T>, T> Consumer for FromExtend<'a, X, T> {
+    type Item = T;
+    type Error = Infallible;
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        self.0.extend(once(item));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
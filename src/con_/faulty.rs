@@ -0,0 +1,193 @@
+use core::cmp::min;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::con::{Consumer, BulkConsumer};
+
+/// The failures a `FaultyConsumer` injects. The `Default` injects none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConsumeFaults {
+    /// Emit an error instead of consuming any items once this many have been consumed.
+    pub error_after: Option<usize>,
+    /// Emit an error on the given call to `flush`, counting from one.
+    pub error_on_flush: Option<NonZeroUsize>,
+    /// Shorten the buffers returned by `consumer_slots` to a single item.
+    pub single_item_slots: bool,
+    /// Consume at most this many items per call to `bulk_consume`.
+    pub max_bulk_consume: Option<NonZeroUsize>,
+}
+
+/// Wraps a consumer and injects failures into it, according to some `ConsumeFaults`.
+///
+/// Panics if any method is called after an error has been emitted, be it an injected one or one
+/// of the wrapped consumer.
+#[derive(Debug)]
+pub struct FaultyConsumer<I, E> {
+    inner: I,
+    faults: ConsumeFaults,
+    // the injected error
+    error: E,
+    consumed: usize,
+    flushed: usize,
+    failed: bool,
+}
+
+impl<I, E> FaultyConsumer<I, E> {
+    /// Creates a wrapper that injects the given faults, emitting clones of `error` for them.
+    pub fn new(inner: I, faults: ConsumeFaults, error: E) -> Self {
+        FaultyConsumer {
+            inner,
+            faults,
+            error,
+            consumed: 0,
+            flushed: 0,
+            failed: false,
+        }
+    }
+
+    /// Returns whether an error has been emitted.
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    fn check_not_failed(&self, method: &str) {
+        if self.failed {
+            panic!("FaultyConsumer: `{}` was called after an error had been emitted", method);
+        }
+    }
+
+    // How many more items may be consumed before the injected error.
+    fn allowance(&self) -> usize {
+        match self.faults.error_after {
+            Some(n) => n.saturating_sub(self.consumed),
+            None => usize::MAX,
+        }
+    }
+}
+
+impl<I, E: Clone> FaultyConsumer<I, E> {
+    fn fail<T>(&mut self, result: Result<T, E>) -> Result<T, E> {
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    fn inject<T>(&mut self) -> Result<T, E> {
+        self.failed = true;
+        Err(self.error.clone())
+    }
+}
+
+impl<I, E> Wrapper<I> for FaultyConsumer<I, E> {
+    fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I, E> AsRef<I> for FaultyConsumer<I, E> {
+    fn as_ref(&self) -> &I {
+        &self.inner
+    }
+}
+
+impl<I, E> AsMut<I> for FaultyConsumer<I, E> {
+    fn as_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+}
+
+impl<I: Consumer<Error = E>, E: Clone> Consumer for FaultyConsumer<I, E> {
+    type Item = I::Item;
+    type Error = E;
+
+    fn consume(&mut self, item: Self::Item) -> Result<(), Self::Error> {
+        self.check_not_failed("consume");
+        if self.allowance() == 0 {
+            return self.inject();
+        }
+
+        let result = self.inner.consume(item);
+        if result.is_ok() {
+            self.consumed += 1;
+        }
+        self.fail(result)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.check_not_failed("flush");
+        self.flushed += 1;
+        if self.faults.error_on_flush.map_or(false, |k| k.get() == self.flushed) {
+            return self.inject();
+        }
+
+        let result = self.inner.flush();
+        self.fail(result)
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.inner.capacity_hint();
+        if self.faults.error_after.is_none() {
+            return (lower, upper);
+        }
+
+        let allowance = self.allowance();
+        (min(lower, allowance), Some(upper.map_or(allowance, |upper| min(upper, allowance))))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+}
+
+impl<I: BulkConsumer<Error = E>, E: Clone> BulkConsumer for FaultyConsumer<I, E> where I::Item: Copy {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        self.check_not_failed("consumer_slots");
+        let mut allowance = self.allowance();
+        if allowance == 0 {
+            return self.inject();
+        }
+        if self.faults.single_item_slots {
+            allowance = 1;
+        }
+
+        match self.inner.consumer_slots() {
+            Ok(slots) => {
+                let len = min(slots.len_(), allowance);
+                Ok(unsafe { Slice1::from_slice_unchecked_mut(&mut slots[..len]) })
+            }
+            Err(e) => {
+                self.failed = true;
+                Err(e)
+            }
+        }
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.check_not_failed("did_consume");
+        self.consumed += amount.get();
+        self.inner.did_consume(amount)
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        self.check_not_failed("bulk_consume");
+        let mut allowance = self.allowance();
+        if allowance == 0 {
+            return self.inject();
+        }
+        if let Some(max) = self.faults.max_bulk_consume {
+            allowance = min(allowance, max.get());
+        }
+
+        let len = min(data.len_(), allowance);
+        let data = unsafe { Slice1::from_slice_unchecked(&data[..len]) };
+        let result = self.inner.bulk_consume(data);
+        if let Ok(amount) = result {
+            self.consumed += amount.get();
+        }
+        self.fail(result)
+    }
+}
@@ -0,0 +1,144 @@
+extern crate maybe_std as base;
+
+use base::num::NonZeroUsize;
+use base::mem::MaybeUninit;
+use base::vec::Vec;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::allocator::*;
+use crate::con::{Consumer, BulkConsumer};
+use crate::Infallible;
+
+/// Collects data and can at any point be converted into a `Vec<T>`.
+pub struct IntoVec<T, A: Allocator = Global>(VecIn<T, A>);
+
+impl<T> IntoVec<T> {
+    /// Create a new `IntoVec`.
+    pub fn new() -> Self {
+        IntoVec(in_global(Vec::new()))
+    }
+
+    /// Create a new `IntoVec` with room for at least `capacity` items before it has to
+    /// reallocate.
+    pub fn with_capacity(capacity: usize) -> Self {
+        IntoVec(in_global(Vec::with_capacity(capacity)))
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<T, A: Allocator> IntoVec<T, A> {
+    /// Create a new `IntoVec` that allocates with the given allocator.
+    pub fn new_in(alloc: A) -> Self {
+        IntoVec(Vec::new_in(alloc))
+    }
+
+    /// Create a new `IntoVec` that allocates with the given allocator, with room for at least
+    /// `capacity` items before it has to reallocate.
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        IntoVec(Vec::with_capacity_in(capacity, alloc))
+    }
+
+    pub fn into_vec(self) -> Vec<T, A> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<T> IntoVec<T> {
+    pub fn into_vec(self) -> Vec<T> {
+        out_of_global(self.0)
+    }
+}
+
+impl<T, A: Allocator> Consumer for IntoVec<T, A> {
+    type Item = T;
+    type Error = Infallible;
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        Ok(self.0.push(item))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
+}
+
+impl<T: Copy, A: Allocator> BulkConsumer for IntoVec<T, A> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        if self.0.capacity() == self.0.len() {
+            // Without a hint via `reserve`, fall back to the amortized growth of `Vec`.
+            self.0.reserve(1);
+        }
+
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(self.0.spare_capacity_mut()) })
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        let len = self.0.len();
+        self.0.set_len(len + amount.get());
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        self.0.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn bulk_consume_vectored(&mut self, fst: &Slice1<Self::Item>, snd: &[Self::Item]) -> Result<NonZeroUsize, Self::Error> {
+        self.0.reserve(fst.len_() + snd.len());
+        self.0.extend_from_slice(fst);
+        self.0.extend_from_slice(snd);
+        Ok(unsafe { NonZeroUsize::new_unchecked(fst.len_() + snd.len()) })
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<T, A: Allocator> Wrapper<Vec<T, A>> for IntoVec<T, A> {
+    fn into_inner(self) -> Vec<T, A> {
+        self.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<T, A: Allocator> AsRef<Vec<T, A>> for IntoVec<T, A> {
+    fn as_ref(&self) -> &Vec<T, A> {
+        &self.0
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<T, A: Allocator> AsMut<Vec<T, A>> for IntoVec<T, A> {
+    fn as_mut(&mut self) -> &mut Vec<T, A> {
+        &mut self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<T> Wrapper<Vec<T>> for IntoVec<T> {
+    fn into_inner(self) -> Vec<T> {
+        out_of_global(self.0)
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<T> AsRef<Vec<T>> for IntoVec<T> {
+    fn as_ref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<T> AsMut<Vec<T>> for IntoVec<T> {
+    fn as_mut(&mut self) -> &mut Vec<T> {
+        &mut self.0
+    }
+}
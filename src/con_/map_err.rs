@@ -51,6 +51,14 @@ impl<I, F, T, E, E2> Consumer for MapErr<I, F> where
             Err(e) => Err(self.1(e))
         }
     }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        self.0.capacity_hint()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
 }
 
 impl<I, F, T, E, E2> BulkConsumer for MapErr<I, F> where
@@ -68,4 +76,25 @@ impl<I, F, T, E, E2> BulkConsumer for MapErr<I, F> where
     unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
         self.0.did_consume(amount)
     }
+
+    fn consumer_slots_vectored(&mut self) -> Result<(&mut Slice1<MaybeUninit<Self::Item>>, &mut [MaybeUninit<Self::Item>]), Self::Error> {
+        match self.0.consumer_slots_vectored() {
+            Ok(s) => Ok(s),
+            Err(e) => Err(self.1(e))
+        }
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        match self.0.bulk_consume(data) {
+            Ok(amount) => Ok(amount),
+            Err(e) => Err(self.1(e))
+        }
+    }
+
+    fn bulk_consume_vectored(&mut self, fst: &Slice1<Self::Item>, snd: &[Self::Item]) -> Result<NonZeroUsize, Self::Error> {
+        match self.0.bulk_consume_vectored(fst, snd) {
+            Ok(amount) => Ok(amount),
+            Err(e) => Err(self.1(e))
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use core::convert::{AsRef, AsMut};
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::con::{Consumer, BulkConsumer};
+use crate::maybe_uninit_slice_mut;
+
+/// Creates a consumer which places consumed data in the given storage, e.g. a `Box<[T]>`, a
+/// `Vec<T>` or an array.
+pub fn owned_cursor<S: AsMut<[T]>, T>(mut s: S) -> OwnedCursor<S, T> {
+    let len = s.as_mut().len();
+    OwnedCursor(s, 0, len, PhantomData)
+}
+
+/// Consumes data into some storage it owns. Unwrapping yields the storage and the number of items
+/// that have been consumed.
+// The third field caches the length of the storage, which `capacity_hint` cannot obtain through
+// a shared reference to storage that is only `AsMut`.
+pub struct OwnedCursor<S, T>(S, usize, usize, PhantomData<T>);
+
+impl<S, T> OwnedCursor<S, T> {
+    /// Returns the number of items that have been consumed so far.
+    pub fn position(&self) -> usize {
+        self.1
+    }
+}
+
+impl<S, T> Wrapper<(S, usize)> for OwnedCursor<S, T> {
+    fn into_inner(self) -> (S, usize) {
+        (self.0, self.1)
+    }
+}
+
+impl<S: AsRef<[T]>, T> AsRef<[T]> for OwnedCursor<S, T> {
+    fn as_ref(&self) -> &[T] {
+        self.0.as_ref()
+    }
+}
+
+impl<S: AsMut<[T]>, T> AsMut<[T]> for OwnedCursor<S, T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self.0.as_mut()
+    }
+}
+
+impl<S: AsMut<[T]>, T> Consumer for OwnedCursor<S, T> {
+    type Item = T;
+    /// Emitted when the end of the storage has been reached.
+    type Error = ();
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        let s = self.0.as_mut();
+        if s.len() == self.1 {
+            Err(())
+        } else {
+            s[self.1] = item;
+            self.1 += 1;
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.2 - self.1;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<S: AsMut<[T]>, T: Copy> BulkConsumer for OwnedCursor<S, T> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        let s = self.0.as_mut();
+        if s.len() == self.1 {
+            Err(())
+        } else {
+            Ok(unsafe { Slice1::from_slice_unchecked_mut(maybe_uninit_slice_mut(&mut s[self.1..])) })
+        }
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.1 += amount.get();
+    }
+}
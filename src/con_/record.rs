@@ -0,0 +1,297 @@
+extern crate maybe_std as base;
+
+use base::vec::Vec;
+use core::cmp::min;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::con::*;
+use crate::write_slice;
+
+/// The methods of a `BulkConsumer` that can emit an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConsumeMethod {
+    Consume,
+    ConsumerSlots,
+    BulkConsume,
+    Flush,
+}
+
+/// A single call to a method of a `BulkConsumer`, as logged by a `Recorder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConsumeEvent {
+    /// `consume` accepted an item.
+    Consume,
+    /// `consumer_slots` returned a buffer of the given length, or `consumer_slots_vectored`
+    /// returned buffers of the given combined length.
+    ConsumerSlots(NonZeroUsize),
+    /// `did_consume` was called with the given amount.
+    DidConsume(NonZeroUsize),
+    /// `bulk_consume` was given a buffer of the first length, and consumed the second amount.
+    BulkConsume(NonZeroUsize, NonZeroUsize),
+    /// `flush` returned successfully.
+    Flush,
+    /// The given method emitted an error.
+    Error(ConsumeMethod),
+}
+
+/// Wraps a consumer and logs every method call into a list of `ConsumeEvent`s.
+#[derive(Debug)]
+pub struct Recorder<C> {
+    inner: C,
+    events: Vec<ConsumeEvent>,
+}
+
+impl<C> Recorder<C> {
+    pub fn new(inner: C) -> Self {
+        Recorder {
+            inner,
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the events that have been recorded so far.
+    pub fn events(&self) -> &[ConsumeEvent] {
+        &self.events
+    }
+
+    /// Returns the wrapped consumer and the recorded events.
+    pub fn into_parts(self) -> (C, Vec<ConsumeEvent>) {
+        (self.inner, self.events)
+    }
+
+    fn record<T, E>(&mut self, result: Result<T, E>, method: ConsumeMethod, event: ConsumeEvent) -> Result<T, E> {
+        match result {
+            Ok(_) => self.events.push(event),
+            Err(_) => self.events.push(ConsumeEvent::Error(method)),
+        }
+        result
+    }
+}
+
+impl<C> Wrapper<C> for Recorder<C> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C> AsRef<C> for Recorder<C> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C> AsMut<C> for Recorder<C> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: Consumer> Consumer for Recorder<C> {
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn consume(&mut self, item: Self::Item) -> Result<(), Self::Error> {
+        let result = self.inner.consume(item);
+        self.record(result, ConsumeMethod::Consume, ConsumeEvent::Consume)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let result = self.inner.flush();
+        self.record(result, ConsumeMethod::Flush, ConsumeEvent::Flush)
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        self.inner.capacity_hint()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+}
+
+impl<C: BulkConsumer> BulkConsumer for Recorder<C> where C::Item: Copy {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        match self.inner.consumer_slots() {
+            Ok(slots) => {
+                self.events.push(ConsumeEvent::ConsumerSlots(slots.len()));
+                Ok(slots)
+            }
+            Err(e) => {
+                self.events.push(ConsumeEvent::Error(ConsumeMethod::ConsumerSlots));
+                Err(e)
+            }
+        }
+    }
+
+    fn consumer_slots_vectored(&mut self) -> Result<(&mut Slice1<MaybeUninit<Self::Item>>, &mut [MaybeUninit<Self::Item>]), Self::Error> {
+        match self.inner.consumer_slots_vectored() {
+            Ok((fst, snd)) => {
+                let len = unsafe { NonZeroUsize::new_unchecked(fst.len_() + snd.len()) };
+                self.events.push(ConsumeEvent::ConsumerSlots(len));
+                Ok((fst, snd))
+            }
+            Err(e) => {
+                self.events.push(ConsumeEvent::Error(ConsumeMethod::ConsumerSlots));
+                Err(e)
+            }
+        }
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.events.push(ConsumeEvent::DidConsume(amount));
+        self.inner.did_consume(amount)
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        match self.inner.bulk_consume(data) {
+            Ok(amount) => {
+                self.events.push(ConsumeEvent::BulkConsume(data.len(), amount));
+                Ok(amount)
+            }
+            Err(e) => {
+                self.events.push(ConsumeEvent::Error(ConsumeMethod::BulkConsume));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Emitted by a `Replayer` when a consumer does not behave as recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the first event that could not be reproduced.
+    pub index: usize,
+}
+
+/// Performs a recorded sequence of `ConsumeEvent`s on a fresh consumer, checking that it behaves
+/// exactly like the recorded one.
+#[derive(Debug, Clone, Copy)]
+pub struct Replayer<'a>(&'a [ConsumeEvent]);
+
+impl<'a> Replayer<'a> {
+    pub fn new(events: &'a [ConsumeEvent]) -> Self {
+        Replayer(events)
+    }
+
+    /// Performs the recorded method calls on the given consumer, feeding it the given items in
+    /// order, and stopping at the first call with a different outcome than recorded. Running out
+    /// of items also counts as a divergence. Slots are requested via `consumer_slots_vectored`,
+    /// and match a recorded length if either the first buffer or both buffers together have it.
+    pub fn replay<C: BulkConsumer>(&self, c: &mut C, items: &[C::Item]) -> Result<(), Divergence> where C::Item: Copy {
+        let mut position = 0;
+        let mut events = self.0.iter().enumerate().peekable();
+
+        while let Some((index, event)) = events.next() {
+            let divergence = Divergence { index };
+            match *event {
+                ConsumeEvent::Consume => {
+                    let item = *items.get(position).ok_or(divergence)?;
+                    c.consume(item).map_err(|_| divergence)?;
+                    position += 1;
+                }
+                ConsumeEvent::ConsumerSlots(len) => {
+                    // The recorded call might have been vectored or not.
+                    let (fst, snd) = c.consumer_slots_vectored().map_err(|_| divergence)?;
+                    if fst.len() != len && fst.len_() + snd.len() != len.get() {
+                        return Err(divergence);
+                    }
+
+                    if let Some((index, ConsumeEvent::DidConsume(amount))) = events.peek() {
+                        let divergence = Divergence { index: *index };
+                        let amount = *amount;
+                        let data = items.get(position..position + amount.get()).ok_or(divergence)?;
+                        if amount > len {
+                            return Err(divergence);
+                        }
+                        let split = min(amount.get(), fst.len_());
+                        write_slice(&mut fst[..split], &data[..split]);
+                        write_slice(&mut snd[..amount.get() - split], &data[split..]);
+                        unsafe { c.did_consume(amount) };
+                        position += amount.get();
+                        events.next();
+                    }
+                }
+                // Data can only be placed in a consumer via the slots it returned.
+                ConsumeEvent::DidConsume(_) => return Err(divergence),
+                ConsumeEvent::BulkConsume(len, amount) => {
+                    let data = items.get(position..position + len.get()).ok_or(divergence)?;
+                    let data = unsafe { Slice1::from_slice_unchecked(data) };
+                    if c.bulk_consume(data).map_err(|_| divergence)? != amount {
+                        return Err(divergence);
+                    }
+                    position += amount.get();
+                }
+                ConsumeEvent::Flush => c.flush().map_err(|_| divergence)?,
+                ConsumeEvent::Error(method) => {
+                    let failed = match method {
+                        ConsumeMethod::Consume => match items.get(position) {
+                            Some(item) => c.consume(*item).is_err(),
+                            None => return Err(divergence),
+                        }
+                        ConsumeMethod::ConsumerSlots => c.consumer_slots().is_err(),
+                        ConsumeMethod::BulkConsume => match Slice1::from_slice(&items[position..]) {
+                            Some(data) => c.bulk_consume(data).is_err(),
+                            None => return Err(divergence),
+                        }
+                        ConsumeMethod::Flush => c.flush().is_err(),
+                    };
+                    if !failed {
+                        return Err(divergence);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ConsumeOperations {
+    /// Converts recorded events into the corresponding scramble operations, so that a recorded
+    /// interaction can be rerun under a `ScrambleConsumer`. Returns `None` if there are no
+    /// operations other than `flush`.
+    pub fn from_events(events: &[ConsumeEvent]) -> Option<Self> {
+        let mut operations = Vec::new();
+        let mut events = events.iter().peekable();
+
+        while let Some(event) = events.next() {
+            match *event {
+                ConsumeEvent::Consume | ConsumeEvent::Error(ConsumeMethod::Consume) => {
+                    operations.push(ConsumeOperation::Consume);
+                }
+                ConsumeEvent::ConsumerSlots(len) => {
+                    let amount = match events.peek() {
+                        Some(ConsumeEvent::DidConsume(amount)) => {
+                            let amount = *amount;
+                            events.next();
+                            amount
+                        }
+                        _ => len,
+                    };
+                    operations.push(ConsumeOperation::ConsumerSlots(amount));
+                }
+                ConsumeEvent::Error(ConsumeMethod::ConsumerSlots) => {
+                    operations.push(ConsumeOperation::ConsumerSlots(NonZeroUsize::new(1).unwrap()));
+                }
+                ConsumeEvent::DidConsume(amount) => {
+                    operations.push(ConsumeOperation::ConsumerSlots(amount));
+                }
+                ConsumeEvent::BulkConsume(len, _) => {
+                    operations.push(ConsumeOperation::BulkConsume(len));
+                }
+                ConsumeEvent::Error(ConsumeMethod::BulkConsume) => {
+                    operations.push(ConsumeOperation::BulkConsume(NonZeroUsize::new(1).unwrap()));
+                }
+                ConsumeEvent::Flush | ConsumeEvent::Error(ConsumeMethod::Flush) => {
+                    operations.push(ConsumeOperation::Flush);
+                }
+            }
+        }
+
+        ConsumeOperations::new(operations.into_boxed_slice())
+    }
+}
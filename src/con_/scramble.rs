@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 use maybe_std::{
     boxed::Box,
+    vec::Vec,
     cmp::min,
     mem::MaybeUninit,
     num::NonZeroUsize,
@@ -9,12 +10,16 @@ use maybe_std::{
 use slice_n::Slice1;
 use wrapper::Wrapper;
 
+#[cfg(feature = "arbitrary")]
 use arbitrary::{Arbitrary, Error, Unstructured};
 
 use crate::*;
+use crate::prng::SplitMix64;
+use crate::allocator::*;
 use ringbuffer::*;
 
-#[derive(Debug, PartialEq, Eq, Arbitrary)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 pub enum ConsumeOperation {
     Consume,
     ConsumerSlots(NonZeroUsize),
@@ -44,6 +49,31 @@ impl ConsumeOperations {
     }
 }
 
+impl ConsumeOperations {
+    /// Deterministically derives between one and sixteen operations from the given seed.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let len = 1 + rng.below(16);
+        let mut operations = Vec::with_capacity(len);
+        for _ in 0..len {
+            let op = match rng.below(4) {
+                0 => ConsumeOperation::Consume,
+                1 => ConsumeOperation::ConsumerSlots(rng.amount()),
+                2 => ConsumeOperation::BulkConsume(rng.amount()),
+                _ => ConsumeOperation::Flush,
+            };
+            operations.push(op);
+        }
+
+        if operations.iter().all(|op| *op == ConsumeOperation::Flush) {
+            operations[0] = ConsumeOperation::Consume;
+        }
+
+        ConsumeOperations(operations.into_boxed_slice())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
 impl<'a> Arbitrary<'a> for ConsumeOperations {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self, Error> {
         match Self::new(Arbitrary::arbitrary(u)?) {
@@ -59,9 +89,9 @@ impl<'a> Arbitrary<'a> for ConsumeOperations {
 }
 
 #[derive(Debug)]
-pub struct ScrambleConsumer<I, T> {
+pub struct ScrambleConsumer<I, T, A: Allocator = Global> {
     inner: I,
-    buf: FixedBuffer<T>,
+    buf: FixedBuffer<T, A>,
     operations: Box<[ConsumeOperation]>,
     operations_index: usize,
 }
@@ -75,9 +105,40 @@ impl<I, T> ScrambleConsumer<I, T> {
             operations_index: 0,
         }
     }
+
+    /// Creates a scrambling wrapper whose operations and buffer capacity are derived
+    /// deterministically from the given seed. Running a test over many seeds and reporting the
+    /// failing one allows for reproducible scrambling in ordinary unit tests.
+    pub fn from_seed(inner: I, seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let capacity = NonZeroUsize::new(1 + rng.below(64)).unwrap();
+        Self::new(inner, ConsumeOperations::from_seed(rng.next_u64()), capacity)
+    }
 }
 
-impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E> Consumer for ScrambleConsumer<I, T> {
+#[cfg(feature = "unstable")]
+impl<I, T, A: Allocator> ScrambleConsumer<I, T, A> {
+    /// Like `new`, but allocates the buffer with the given allocator.
+    pub fn new_in(inner: I, operations: ConsumeOperations, capacity: NonZeroUsize, alloc: A) -> Self {
+        ScrambleConsumer {
+            inner,
+            buf: FixedBuffer::new_in(capacity, alloc),
+            operations: operations.0,
+            operations_index: 0,
+        }
+    }
+
+    /// Like `from_seed`, but allocates the buffer with the given allocator. The derived
+    /// operations are still allocated with the global allocator, just like those passed to
+    /// `new_in`.
+    pub fn from_seed_in(inner: I, seed: u64, alloc: A) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let capacity = NonZeroUsize::new(1 + rng.below(64)).unwrap();
+        Self::new_in(inner, ConsumeOperations::from_seed(rng.next_u64()), capacity, alloc)
+    }
+}
+
+impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E, A: Allocator> Consumer for ScrambleConsumer<I, T, A> {
     type Item = T;
     type Error = E;
 
@@ -96,9 +157,21 @@ impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E> Consumer for Scra
         }
         self.inner.flush()
     }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        // Items only reach the inner consumer once the buffer is full, so the free buffer space
+        // can always be filled.
+        let free = self.buf.get_capacity().get() - self.buf.get_amount();
+        let (lower, upper) = self.inner.capacity_hint();
+        (lower.saturating_add(free), upper.and_then(|upper| upper.checked_add(free)))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
 }
 
-impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E> BulkConsumer for ScrambleConsumer<I, T> {
+impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E, A: Allocator> BulkConsumer for ScrambleConsumer<I, T, A> {
     fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
         while self.buf.get_capacity().get() == self.buf.get_amount() {
             self.perform_operation()?;
@@ -115,7 +188,7 @@ impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E> BulkConsumer for
     }
 }
 
-impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E> ScrambleConsumer<I, T> {
+impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E, A: Allocator> ScrambleConsumer<I, T, A> {
     fn perform_operation(&mut self) -> Result<(), E> {
         debug_assert!(self.buf.get_amount() > 0);
 
@@ -145,19 +218,19 @@ impl<I: BulkConsumer<Item = T, Error = E>, T: Copy + Debug, E> ScrambleConsumer<
     }
 }
 
-impl<I, T> Wrapper<I> for ScrambleConsumer<I, T> {
+impl<I, T, A: Allocator> Wrapper<I> for ScrambleConsumer<I, T, A> {
     fn into_inner(self) -> I {
         self.inner
     }
 }
 
-impl<I, T> AsRef<I> for ScrambleConsumer<I, T> {
+impl<I, T, A: Allocator> AsRef<I> for ScrambleConsumer<I, T, A> {
     fn as_ref(&self) -> &I {
         &self.inner
     }
 }
 
-impl<I, T> AsMut<I> for ScrambleConsumer<I, T> {
+impl<I, T, A: Allocator> AsMut<I> for ScrambleConsumer<I, T, A> {
     fn as_mut(&mut self) -> &mut I {
         &mut self.inner
     }
@@ -0,0 +1,108 @@
+use core::convert::{AsRef, AsMut};
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::{Consumer, BulkConsumer};
+use crate::time::{Clock, TimeoutError};
+
+/// Wraps a consumer such that `consume`, `consumer_slots` and `flush` fail with
+/// `TimeoutError::TimedOut` once the given deadline has passed, according to the given clock.
+///
+/// The deadline is only checked before delegating to the wrapped consumer, an operation of the
+/// wrapped consumer that blocks is not interrupted.
+pub fn with_timeout<C, K: Clock>(inner: C, clock: K, deadline: K::Instant) -> WithTimeout<C, K> {
+    WithTimeout { inner, clock, deadline }
+}
+
+/// A consumer whose operations fail with `TimeoutError::TimedOut` once a deadline has passed.
+pub struct WithTimeout<C, K: Clock> {
+    inner: C,
+    clock: K,
+    deadline: K::Instant,
+}
+
+impl<C, K: Clock> WithTimeout<C, K> {
+    /// Returns the instant from which on the consumer times out.
+    pub fn deadline(&self) -> K::Instant {
+        self.deadline
+    }
+
+    /// Sets the instant from which on the consumer times out. The clock is checked against it
+    /// before delegating to the wrapped consumer.
+    pub fn set_deadline(&mut self, deadline: K::Instant) {
+        self.deadline = deadline;
+    }
+
+    fn check<E>(&self) -> Result<(), TimeoutError<E>> {
+        if self.clock.now() >= self.deadline {
+            Err(TimeoutError::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<C, K: Clock> Wrapper<C> for WithTimeout<C, K> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C, K: Clock> AsRef<C> for WithTimeout<C, K> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C, K: Clock> AsMut<C> for WithTimeout<C, K> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: Consumer, K: Clock> Consumer for WithTimeout<C, K> {
+    type Item = C::Item;
+    type Error = TimeoutError<C::Error>;
+
+    fn consume(&mut self, item: Self::Item) -> Result<(), Self::Error> {
+        self.check()?;
+        self.inner.consume(item).map_err(TimeoutError::Inner)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.check()?;
+        self.inner.flush().map_err(TimeoutError::Inner)
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        // The deadline may pass at any time.
+        (0, self.inner.capacity_hint().1)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+}
+
+impl<C, K> BulkConsumer for WithTimeout<C, K> where
+    C: BulkConsumer,
+    C::Item: Copy,
+    K: Clock,
+{
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        self.check()?;
+        self.inner.consumer_slots().map_err(TimeoutError::Inner)
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.inner.did_consume(amount)
+    }
+
+    fn consumer_slots_vectored(&mut self) -> Result<(&mut Slice1<MaybeUninit<Self::Item>>, &mut [MaybeUninit<Self::Item>]), Self::Error> {
+        self.check()?;
+        self.inner.consumer_slots_vectored().map_err(TimeoutError::Inner)
+    }
+}
@@ -0,0 +1,494 @@
+//! Reading and writing comma-separated values, as specified in RFC 4180, and similar formats such
+//! as tab-separated values.
+//!
+//! A `Reader` is a `Producer` of the fields and record boundaries of the text that a wrapped
+//! `BulkProducer` produces, passing on the contents of fields in chunks. With the `alloc` feature,
+//! `records` turns a reader into a `Producer` of whole records. A `Writer` is a `Consumer` of
+//! records that writes them into a wrapped `BulkConsumer`, quoting fields where necessary.
+#[cfg(feature = "alloc")]
+extern crate maybe_std as base;
+
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::marker::PhantomData;
+use core::num::NonZeroUsize;
+
+#[cfg(feature = "alloc")]
+use base::vec::Vec;
+
+use wrapper::Wrapper;
+
+use crate::*;
+
+/// Configures the format of a `Reader` or `Writer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    /// The byte that separates the fields of a record.
+    pub delimiter: u8,
+    /// The byte that encloses quoted fields. Within them, it is escaped by doubling it.
+    pub quote: u8,
+    /// Whether a writer ends records with CRLF, as RFC 4180 demands, or with LF only. Readers
+    /// accept CRLF, LF and CR.
+    pub crlf: bool,
+}
+
+impl Config {
+    /// Comma-separated values as specified in RFC 4180.
+    pub const CSV: Config = Config { delimiter: b',', quote: b'"', crlf: true };
+    /// Tab-separated values, with records ended by LF.
+    pub const TSV: Config = Config { delimiter: b'\t', quote: b'"', crlf: false };
+
+    fn needs_quotes(&self, field: &[u8]) -> bool {
+        field.iter().any(|byte| *byte == self.delimiter || *byte == self.quote || *byte == b'\r' || *byte == b'\n')
+    }
+}
+
+/// A piece of a CSV text, as produced by a `Reader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CsvToken {
+    /// A nonempty chunk of the contents of the current field, of the given length, available from
+    /// `Reader::chunk`. Quotes have been removed and unescaped.
+    Chunk(usize),
+    /// The end of a field.
+    FieldEnd,
+    /// The end of a record, following the `FieldEnd` of its last field.
+    RecordEnd,
+}
+
+/// Emitted by a `Reader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadError<E> {
+    /// The wrapped producer emitted an error, e.g. because the input has ended. All records of
+    /// the input have been produced before.
+    Inner(E),
+    /// A quote within an unquoted field, or a byte other than a quote, delimiter or line break
+    /// after the closing quote of a quoted field. `Reader::offset` is the offset of the byte.
+    InvalidQuote,
+    /// The input ended within a quoted field.
+    Incomplete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldState {
+    // at the start of a field, nothing has been read
+    Start,
+    Unquoted,
+    Quoted,
+    // after a quote within a quoted field, which either closes the field or escapes a quote
+    QuoteInQuoted,
+}
+
+// Whether a step consumed the byte, and the token it completed.
+enum Step {
+    Consumed(Option<CsvToken>),
+    // The byte has not been consumed, it is processed again by the next step.
+    Kept(CsvToken),
+}
+
+struct ReadState {
+    config: Config,
+    field: FieldState,
+    // whether the current record has any fields, so that its end has to be produced
+    in_record: bool,
+    // whether the previous byte was a CR that ended a record, so that a following LF is skipped
+    after_cr: bool,
+    // the number of bytes of the current chunk in the buffer
+    len: usize,
+    // the length of the most recently produced chunk
+    chunk_len: usize,
+    // a token to produce before reading further input
+    pending: Option<CsvToken>,
+    // the number of bytes read so far
+    offset: u64,
+}
+
+impl ReadState {
+    fn take_chunk(&mut self) -> CsvToken {
+        self.chunk_len = self.len;
+        self.len = 0;
+        CsvToken::Chunk(self.chunk_len)
+    }
+
+    // Ends the current field, after handing out its last chunk.
+    fn end_field(&mut self, record: bool) -> Step {
+        if self.len > 0 {
+            return Step::Kept(self.take_chunk());
+        }
+
+        self.field = FieldState::Start;
+        if record {
+            self.in_record = false;
+            self.pending = Some(CsvToken::RecordEnd);
+        } else {
+            self.in_record = true;
+        }
+        Step::Consumed(Some(CsvToken::FieldEnd))
+    }
+
+    // Processes a single byte.
+    fn step<E>(&mut self, buffer: &mut [u8], byte: u8) -> Result<Step, ReadError<E>> {
+        if self.len == buffer.len() {
+            return Ok(Step::Kept(self.take_chunk()));
+        } else if self.after_cr {
+            self.after_cr = false;
+            if byte == b'\n' {
+                return Ok(Step::Consumed(None));
+            }
+        }
+
+        let delimiter = byte == self.config.delimiter;
+        let line_break = byte == b'\r' || byte == b'\n';
+        let quote = byte == self.config.quote;
+
+        match self.field {
+            FieldState::Quoted if quote => self.field = FieldState::QuoteInQuoted,
+            FieldState::Quoted => {}
+            FieldState::QuoteInQuoted if quote => self.field = FieldState::Quoted,
+            FieldState::Start if quote => {
+                self.field = FieldState::Quoted;
+                self.in_record = true;
+                return Ok(Step::Consumed(None));
+            }
+            FieldState::Start | FieldState::Unquoted | FieldState::QuoteInQuoted if delimiter || line_break => {
+                let step = self.end_field(line_break);
+                if let (Step::Consumed(_), true) = (&step, byte == b'\r') {
+                    self.after_cr = true;
+                }
+                return Ok(step);
+            }
+            FieldState::Start | FieldState::Unquoted if !quote => {
+                self.field = FieldState::Unquoted;
+                self.in_record = true;
+            }
+            _ => return Err(ReadError::InvalidQuote),
+        }
+
+        if self.field == FieldState::QuoteInQuoted {
+            return Ok(Step::Consumed(None));
+        }
+        buffer[self.len] = byte;
+        self.len += 1;
+        Ok(Step::Consumed(None))
+    }
+
+    // Processes the given bytes until a token is complete. Returns the number of bytes read, and
+    // the token.
+    fn read<E>(&mut self, buffer: &mut [u8], bytes: &[u8]) -> Result<(usize, Option<CsvToken>), ReadError<E>> {
+        let mut read = 0;
+        while read < bytes.len() {
+            if let (FieldState::Unquoted, false) | (FieldState::Quoted, false) = (self.field, self.after_cr) {
+                // Copy all bytes that do not end the field at once.
+                let available = &bytes[read..min(bytes.len(), read + buffer.len() - self.len)];
+                let quoted = self.field == FieldState::Quoted;
+                let run = available.iter()
+                    .position(|byte| {
+                        *byte == self.config.quote || (!quoted && (*byte == self.config.delimiter || *byte == b'\r' || *byte == b'\n'))
+                    })
+                    .unwrap_or(available.len());
+                if run > 0 {
+                    buffer[self.len..self.len + run].copy_from_slice(&available[..run]);
+                    self.len += run;
+                    self.offset += run as u64;
+                    read += run;
+                    continue;
+                }
+            }
+
+            match self.step(buffer, bytes[read])? {
+                Step::Consumed(token) => {
+                    read += 1;
+                    self.offset += 1;
+                    if token.is_some() {
+                        return Ok((read, token));
+                    }
+                }
+                Step::Kept(token) => return Ok((read, Some(token))),
+            }
+        }
+
+        // Chunks do not extend beyond the slots of the wrapped producer.
+        if self.len > 0 {
+            Ok((read, Some(self.take_chunk())))
+        } else {
+            Ok((read, None))
+        }
+    }
+}
+
+/// Creates a reader that produces the fields and records of the text that `inner` produces,
+/// using `buffer` for the chunks of fields. Panics if the buffer is empty.
+pub fn reader<P: Producer, B: AsMut<[u8]>>(inner: P, mut buffer: B, config: Config) -> Reader<P, B> {
+    assert!(!buffer.as_mut().is_empty(), "a CSV reader needs a nonempty buffer");
+    Reader {
+        inner,
+        buffer,
+        state: ReadState {
+            config,
+            field: FieldState::Start,
+            in_record: false,
+            after_cr: false,
+            len: 0,
+            chunk_len: 0,
+            pending: None,
+            offset: 0,
+        },
+        err: None,
+    }
+}
+
+/// Produces the fields and records of the text of a wrapped `BulkProducer`.
+///
+/// Each field is produced as any number of `Chunk`s followed by a `FieldEnd`, the last field of
+/// each record is followed by a `RecordEnd`. An empty line is a record with a single empty field.
+/// The text ends when the wrapped producer emits an error, the last record does not need to be
+/// ended by a line break.
+///
+/// The chunks are copied into the buffer of the reader, which can hold any type that can be
+/// viewed as a mutable slice of bytes. The contents of a `Chunk` are available from `chunk` until
+/// the next call to `produce`.
+pub struct Reader<P: Producer, B> {
+    inner: P,
+    buffer: B,
+    state: ReadState,
+    // the error that ended the input, emitted after the end of the last record
+    err: Option<P::Error>,
+}
+
+impl<P: Producer, B> Reader<P, B> {
+    /// Returns the number of bytes of the input that have been read so far.
+    pub fn offset(&self) -> u64 {
+        self.state.offset
+    }
+}
+
+impl<P: Producer, B: AsRef<[u8]>> Reader<P, B> {
+    /// Returns the contents of the most recently produced `Chunk`, or an empty slice after any
+    /// other token.
+    pub fn chunk(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.state.chunk_len]
+    }
+}
+
+impl<P: Producer, B> Wrapper<P> for Reader<P, B> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Producer, B> AsRef<P> for Reader<P, B> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P: Producer, B> AsMut<P> for Reader<P, B> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]>> Producer for Reader<P, B> {
+    type Item = CsvToken;
+    type Error = ReadError<P::Error>;
+
+    fn produce(&mut self) -> Result<CsvToken, Self::Error> {
+        self.state.chunk_len = 0;
+        if let Some(token) = self.state.pending.take() {
+            return Ok(token);
+        }
+
+        loop {
+            if self.err.is_some() {
+                // The input has ended, so end the last record.
+                return if self.state.len > 0 {
+                    Ok(self.state.take_chunk())
+                } else if self.state.in_record || self.state.field != FieldState::Start {
+                    match self.state.end_field(true) {
+                        Step::Consumed(Some(token)) | Step::Kept(token) => Ok(token),
+                        Step::Consumed(None) => unreachable!(),
+                    }
+                } else {
+                    Err(ReadError::Inner(self.err.take().unwrap()))
+                };
+            }
+
+            let bytes = match self.inner.producer_slots() {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    if self.state.field == FieldState::Quoted {
+                        return Err(ReadError::Incomplete);
+                    }
+                    self.err = Some(e);
+                    continue;
+                }
+            };
+
+            let (read, token) = self.state.read(self.buffer.as_mut(), bytes)?;
+            if let Some(amount) = NonZeroUsize::new(read) {
+                self.inner.did_produce(amount);
+            }
+
+            if let Some(token) = token {
+                return Ok(token);
+            }
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(ReadError::Inner)
+    }
+
+    // Every byte yields at most a chunk, or the end of a field and of a record, and the end of the
+    // input may end a chunk, a field and a record.
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let upper = self.inner.remaining_hint().1.and_then(|upper| upper.checked_mul(2)?.checked_add(3));
+        (self.state.pending.is_some() as usize, upper)
+    }
+}
+
+/// Turns a `Reader` into a `Producer` of whole records, each of which is a `Vec` of its fields.
+#[cfg(feature = "alloc")]
+pub fn records<P: Producer, B>(reader: Reader<P, B>) -> Records<P, B> {
+    Records(reader)
+}
+
+/// Produces the records of a wrapped `Reader`, as a `Vec` of the contents of each field.
+#[cfg(feature = "alloc")]
+pub struct Records<P: Producer, B>(Reader<P, B>);
+
+#[cfg(feature = "alloc")]
+impl<P: Producer, B> Wrapper<Reader<P, B>> for Records<P, B> {
+    fn into_inner(self) -> Reader<P, B> {
+        self.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: Producer, B> AsRef<Reader<P, B>> for Records<P, B> {
+    fn as_ref(&self) -> &Reader<P, B> {
+        &self.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: Producer, B> AsMut<Reader<P, B>> for Records<P, B> {
+    fn as_mut(&mut self) -> &mut Reader<P, B> {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]> + AsRef<[u8]>> Producer for Records<P, B> {
+    type Item = Vec<Vec<u8>>;
+    type Error = ReadError<P::Error>;
+
+    fn produce(&mut self) -> Result<Self::Item, Self::Error> {
+        let mut record = Vec::new();
+        let mut field = Vec::new();
+        loop {
+            match self.0.produce()? {
+                CsvToken::Chunk(_) => field.extend_from_slice(self.0.chunk()),
+                CsvToken::FieldEnd => record.push(core::mem::take(&mut field)),
+                CsvToken::RecordEnd => return Ok(record),
+            }
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.0.slurp()
+    }
+
+    // Every record ends with a `RecordEnd`.
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.remaining_hint().1)
+    }
+}
+
+/// Creates a writer that writes all consumed records into `inner`.
+pub fn writer<C, R>(inner: C, config: Config) -> Writer<C, R> {
+    Writer {
+        inner,
+        config,
+        records: PhantomData,
+    }
+}
+
+/// Consumes records and writes them into a wrapped `BulkConsumer`. A record can be anything that
+/// can be iterated over, as long as its fields can be viewed as slices of bytes, e.g. a
+/// `&[&str]` or a `Vec<Vec<u8>>`.
+///
+/// Fields are enclosed in quotes only if they contain the delimiter, the quote or a line break,
+/// or if a record consists of a single empty field, which is written as two quotes. A record
+/// without any fields is written as an empty line, which a `Reader` reads as a record with a
+/// single empty field.
+pub struct Writer<C, R> {
+    inner: C,
+    config: Config,
+    records: PhantomData<fn(R)>,
+}
+
+impl<C: BulkConsumer<Item = u8>, R> Writer<C, R> {
+    // Writes a single field. An empty field that is the only one of its record is quoted, as it
+    // would otherwise be written as an empty line, i.e. a record without fields.
+    fn write_field(&mut self, field: &[u8], alone: bool) -> Result<(), C::Error> {
+        if !(self.config.needs_quotes(field) || alone && field.is_empty()) {
+            return consume_slice(&mut self.inner, field);
+        }
+
+        let quote = self.config.quote;
+        self.inner.consume(quote)?;
+        for (i, part) in field.split(|byte| *byte == quote).enumerate() {
+            if i > 0 {
+                consume_slice(&mut self.inner, &[quote, quote])?;
+            }
+            consume_slice(&mut self.inner, part)?;
+        }
+        self.inner.consume(quote)
+    }
+}
+
+impl<C, R> Wrapper<C> for Writer<C, R> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C, R> AsRef<C> for Writer<C, R> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C, R> AsMut<C> for Writer<C, R> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: BulkConsumer<Item = u8>, R: IntoIterator> Consumer for Writer<C, R> where R::Item: AsRef<[u8]> {
+    type Item = R;
+    type Error = C::Error;
+
+    fn consume(&mut self, item: R) -> Result<(), Self::Error> {
+        let mut fields = item.into_iter().peekable();
+        let mut first = true;
+        while let Some(field) = fields.next() {
+            if !first {
+                self.inner.consume(self.config.delimiter)?;
+            }
+            let alone = first && fields.peek().is_none();
+            self.write_field(field.as_ref(), alone)?;
+            first = false;
+        }
+
+        if self.config.crlf {
+            consume_slice(&mut self.inner, b"\r\n")
+        } else {
+            self.inner.consume(b'\n')
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
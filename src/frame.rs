@@ -0,0 +1,54 @@
+// Driving the decoders of the byte-stuffing framings, which decode one frame at a time into a
+// buffer provided by the caller.
+use core::num::NonZeroUsize;
+
+use crate::*;
+
+/// The progress of decoding a single frame, for a wrapped producer emitting errors of type `E`.
+pub(crate) trait FrameState<E> {
+    /// The error of the decoder.
+    type Error;
+
+    /// Returns whether some bytes of the current frame have been read.
+    fn in_frame(&self) -> bool;
+
+    /// Returns the number of decoded bytes in the buffer.
+    fn len(&self) -> usize;
+
+    /// Discards the decoded bytes of the previous frame.
+    fn clear(&mut self);
+
+    /// Decodes the given bytes, up to and including the end of the current frame. Returns the
+    /// number of bytes read, and whether a frame has been completed or an error occurred.
+    fn decode(&mut self, buffer: &mut [u8], bytes: &[u8]) -> (usize, Option<Result<(), Self::Error>>);
+
+    /// Called when the wrapped producer has emitted the given error, returns the error to emit.
+    fn end(&mut self, e: E) -> Self::Error;
+}
+
+/// Decodes the next frame into the buffer, reading its encoding from `inner`, and returns its
+/// length.
+pub(crate) fn produce_frame<P, S>(inner: &mut P, state: &mut S, buffer: &mut [u8]) -> Result<usize, S::Error> where
+    P: BulkProducer<Item = u8>,
+    S: FrameState<P::Error>,
+{
+    if !state.in_frame() {
+        state.clear();
+    }
+
+    loop {
+        let bytes = match inner.producer_slots() {
+            Ok(bytes) => bytes,
+            Err(e) => return Err(state.end(e)),
+        };
+
+        let (read, decoded) = state.decode(buffer, bytes);
+        if let Some(amount) = NonZeroUsize::new(read) {
+            inner.did_produce(amount);
+        }
+
+        if let Some(decoded) = decoded {
+            return decoded.map(|()| state.len());
+        }
+    }
+}
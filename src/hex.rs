@@ -0,0 +1,551 @@
+//! Streaming hexadecimal encoding and decoding, and hexdumps.
+//!
+//! An `Encoder` is a `BulkConsumer` of raw bytes that writes two hex digits per byte into a
+//! wrapped `BulkConsumer`, a `Decoder` is a `BulkProducer` that produces the bytes encoded by the
+//! hex text of a wrapped `BulkProducer`. A `Hexdump` is a `Consumer` of raw bytes that writes a
+//! human-readable dump in the layout of `hexdump -C` into a wrapped `Consumer`.
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::*;
+
+/// Whether to encode the digits `a` to `f` as lower or upper case letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Case {
+    Lower,
+    Upper,
+}
+
+/// Configures the hex text to encode or decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    /// The case of the encoded letters. Decoding accepts both cases regardless.
+    pub case: Case,
+    /// A byte separating the encodings of consecutive bytes, e.g. `b' '` or `b':'`. A decoder with
+    /// a separator requires exactly one separator between any two bytes.
+    pub separator: Option<u8>,
+}
+
+impl Config {
+    pub const LOWER: Config = Config { case: Case::Lower, separator: None };
+    pub const UPPER: Config = Config { case: Case::Upper, separator: None };
+
+    fn digit(&self, nibble: u8) -> u8 {
+        match (nibble, self.case) {
+            (0..=9, _) => b'0' + nibble,
+            (_, Case::Lower) => b'a' + (nibble - 10),
+            (_, Case::Upper) => b'A' + (nibble - 10),
+        }
+    }
+
+    // Encodes a byte into `out`, preceded by the separator unless it is the first byte. Returns
+    // the number of characters.
+    fn encode(&self, byte: u8, first: bool, out: &mut [u8]) -> usize {
+        let mut len = 0;
+        if let (Some(separator), false) = (self.separator, first) {
+            out[0] = separator;
+            len = 1;
+        }
+        out[len] = self.digit(byte >> 4);
+        out[len + 1] = self.digit(byte & 0x0f);
+        len + 2
+    }
+}
+
+fn decode_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Creates an encoder that writes the hex encoding of all consumed bytes into `inner`.
+pub fn encoder<C>(inner: C, config: Config) -> Encoder<C> {
+    Encoder {
+        inner,
+        config,
+        input: [MaybeUninit::uninit()],
+        first: true,
+        output: [0; 3],
+        output_start: 0,
+        output_end: 0,
+    }
+}
+
+/// Consumes bytes and writes their hex encoding into a wrapped `BulkConsumer`.
+pub struct Encoder<C> {
+    inner: C,
+    config: Config,
+    // the slot handed out by `consumer_slots`
+    input: [MaybeUninit<u8>; 1],
+    // whether no byte has been encoded yet
+    first: bool,
+    // characters that have been encoded but not yet consumed by the inner consumer
+    output: [u8; 3],
+    output_start: usize,
+    output_end: usize,
+}
+
+impl<C: BulkConsumer<Item = u8>> Encoder<C> {
+    // Writes all pending characters into the inner consumer.
+    fn drain(&mut self) -> Result<(), C::Error> {
+        while let Some(chars) = Slice1::from_slice(&self.output[self.output_start..self.output_end]) {
+            self.output_start += self.inner.bulk_consume(chars)?.get();
+        }
+        Ok(())
+    }
+
+    fn encode_pending(&mut self, byte: u8) {
+        self.output_end = self.config.encode(byte, self.first, &mut self.output);
+        self.output_start = 0;
+        self.first = false;
+    }
+}
+
+impl<C> Wrapper<C> for Encoder<C> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C> AsRef<C> for Encoder<C> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C> AsMut<C> for Encoder<C> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: BulkConsumer<Item = u8>> Consumer for Encoder<C> {
+    type Item = u8;
+    type Error = C::Error;
+
+    fn consume(&mut self, item: u8) -> Result<(), Self::Error> {
+        self.drain()?;
+        self.encode_pending(item);
+        self.drain()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.drain()?;
+        self.inner.flush()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional.saturating_mul(3))
+    }
+}
+
+impl<C: BulkConsumer<Item = u8>> BulkConsumer for Encoder<C> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        self.drain()?;
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(&mut self.input[..]) })
+    }
+
+    unsafe fn did_consume(&mut self, _amount: NonZeroUsize) {
+        // The characters are written by the next call.
+        self.encode_pending(self.input[0].assume_init());
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        self.drain()?;
+
+        // Encode as many bytes as possible directly into the inner consumer.
+        let slots = self.inner.consumer_slots()?;
+        let mut chars = [0; 3];
+        let mut written = 0;
+        let mut amount = 0;
+        for byte in data.iter() {
+            let len = self.config.encode(*byte, self.first, &mut chars);
+            if written + len > slots.len_() {
+                break;
+            }
+            write_slice(&mut slots[written..written + len], &chars[..len]);
+            written += len;
+            amount += 1;
+            self.first = false;
+        }
+
+        if amount > 0 {
+            unsafe {
+                self.inner.did_consume(NonZeroUsize::new_unchecked(written));
+                Ok(NonZeroUsize::new_unchecked(amount))
+            }
+        } else {
+            // The characters are written by the next call, so that an error of the inner consumer
+            // is reported by the call that caused it.
+            self.encode_pending(data[0]);
+            Ok(unsafe { NonZeroUsize::new_unchecked(1) })
+        }
+    }
+}
+
+/// Emitted by a `Decoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError<E> {
+    /// The wrapped producer emitted an error, e.g. because the text has ended. All bytes encoded
+    /// by the text have been produced before.
+    Inner(E),
+    /// The text contains a byte that is neither a hex digit nor a separator in a valid position.
+    InvalidByte(u8),
+    /// The text ended with a single digit or a separator.
+    IncompleteByte,
+}
+
+// The number of decoded bytes a decoder buffers at most.
+const DECODED_LEN: usize = 32;
+
+struct DecodeState {
+    config: Config,
+    // the first digit of the current byte
+    high: Option<u8>,
+    // whether no byte has been decoded yet
+    first: bool,
+    // whether a separator has been read, which must be followed by a byte
+    separated: bool,
+    // decoded bytes that have not been produced yet
+    decoded: [u8; DECODED_LEN],
+    decoded_start: usize,
+    decoded_end: usize,
+}
+
+impl DecodeState {
+    fn push<E>(&mut self, c: u8) -> Result<(), DecodeError<E>> {
+        if let (Some(separator), None, false, false) = (self.config.separator, self.high, self.first, self.separated) {
+            return if c == separator {
+                self.separated = true;
+                Ok(())
+            } else {
+                Err(DecodeError::InvalidByte(c))
+            };
+        }
+
+        let digit = decode_digit(c).ok_or(DecodeError::InvalidByte(c))?;
+        match self.high.take() {
+            Some(high) => {
+                self.decoded[self.decoded_end] = (high << 4) | digit;
+                self.decoded_end += 1;
+                self.first = false;
+                self.separated = false;
+            }
+            None => self.high = Some(digit),
+        }
+        Ok(())
+    }
+
+    // Called once the text has ended.
+    fn finish<E>(&self) -> Result<(), DecodeError<E>> {
+        if self.high.is_some() || self.separated {
+            Err(DecodeError::IncompleteByte)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Creates a decoder that produces the bytes encoded by the hex text that `inner` produces.
+pub fn decoder<P: Producer>(inner: P, config: Config) -> Decoder<P> {
+    Decoder {
+        inner,
+        state: DecodeState {
+            config,
+            high: None,
+            first: true,
+            separated: false,
+            decoded: [0; DECODED_LEN],
+            decoded_start: 0,
+            decoded_end: 0,
+        },
+    }
+}
+
+/// Produces the bytes encoded by the hex text of a wrapped `BulkProducer`. The text ends when the
+/// wrapped producer emits an error.
+pub struct Decoder<P: Producer> {
+    inner: P,
+    state: DecodeState,
+}
+
+impl<P: BulkProducer<Item = u8>> Decoder<P> {
+    // Decodes further bytes, if there are no decoded bytes left.
+    fn fill(&mut self) -> Result<(), DecodeError<P::Error>> {
+        if self.state.decoded_start < self.state.decoded_end {
+            return Ok(());
+        }
+
+        self.state.decoded_start = 0;
+        self.state.decoded_end = 0;
+        while self.state.decoded_end == 0 {
+            let chars = match self.inner.producer_slots() {
+                Ok(chars) => chars,
+                Err(e) => {
+                    self.state.finish()?;
+                    return Err(DecodeError::Inner(e));
+                }
+            };
+
+            // Every decoded byte takes two digits, one of which may have been read already.
+            let available = min(chars.len_(), DECODED_LEN * 2 - 1);
+            let mut amount = 0;
+            let mut result = Ok(());
+            for c in chars[..available].iter() {
+                result = self.state.push(*c);
+                if result.is_err() {
+                    break;
+                }
+                amount += 1;
+            }
+            if let Some(amount) = NonZeroUsize::new(amount) {
+                self.inner.did_produce(amount);
+            }
+
+            // An invalid character does not change the state and is not marked as produced, so
+            // if some bytes have been decoded before it, it is reported once they have been
+            // produced.
+            if self.state.decoded_end == 0 {
+                result?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P: Producer> Wrapper<P> for Decoder<P> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Producer> AsRef<P> for Decoder<P> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P: Producer> AsMut<P> for Decoder<P> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>> Producer for Decoder<P> {
+    type Item = u8;
+    type Error = DecodeError<P::Error>;
+
+    fn produce(&mut self) -> Result<u8, Self::Error> {
+        self.fill()?;
+        let item = self.state.decoded[self.state.decoded_start];
+        self.state.decoded_start += 1;
+        Ok(item)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(DecodeError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let decoded = self.state.decoded_end - self.state.decoded_start;
+        match self.inner.remaining_hint().1 {
+            Some(chars) => (decoded, (chars / 2 + chars % 2).checked_add(decoded)),
+            None => (decoded, None),
+        }
+    }
+}
+
+impl<P: BulkProducer<Item = u8>> BulkProducer for Decoder<P> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        self.fill()?;
+        Ok(unsafe { Slice1::from_slice_unchecked(&self.state.decoded[self.state.decoded_start..self.state.decoded_end]) })
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.state.decoded_start += amount.get();
+    }
+}
+
+// The number of bytes per hexdump line.
+const LINE_LEN: usize = 16;
+
+/// Creates a consumer that writes a hexdump of all consumed bytes into `inner`.
+pub fn hexdump<C: Consumer<Item = u8>>(inner: C) -> Hexdump<C> {
+    Hexdump {
+        inner,
+        offset: 0,
+        line: [MaybeUninit::uninit(); LINE_LEN],
+        line_len: 0,
+    }
+}
+
+/// Creates a consumer that writes a hexdump of all consumed bytes into a `core::fmt::Write`, e.g.
+/// a `String` or a `core::fmt::Formatter`.
+pub fn hexdump_fmt<W: fmt::Write>(w: W) -> Hexdump<FmtWrite<W>> {
+    hexdump(FmtWrite(w))
+}
+
+/// Consumes bytes and writes a hexdump of them into a wrapped `Consumer`, in the layout of
+/// `hexdump -C`:
+///
+/// ```text
+/// 00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|
+/// 00000010  2a                                                |*|
+/// ```
+///
+/// Each line is written once it is complete, or by the next call if it has been completed by
+/// `did_consume`. Flushing writes the current incomplete line, so bytes consumed afterwards start
+/// on a new line.
+pub struct Hexdump<C> {
+    inner: C,
+    // the offset of the first byte of the current line
+    offset: u64,
+    line: [MaybeUninit<u8>; LINE_LEN],
+    line_len: usize,
+}
+
+impl<C: Consumer<Item = u8>> Hexdump<C> {
+    fn write_line(&mut self) -> Result<(), C::Error> {
+        let line = unsafe { slice_assume_init_ref(&self.line[..self.line_len]) };
+
+        // offset, two spaces, three characters per byte plus an extra space in the middle, space,
+        // bars, ascii, newline
+        let mut out = [b' '; 16 + 2 + 3 * LINE_LEN + 1 + 1 + 2 + LINE_LEN + 1];
+        let mut len = 0;
+
+        let digits = if self.offset >> 32 == 0 { 8 } else { 16 };
+        for i in (0..digits).rev() {
+            out[len] = Config::LOWER.digit(((self.offset >> (4 * i)) & 0x0f) as u8);
+            len += 1;
+        }
+        len += 2;
+
+        for i in 0..LINE_LEN {
+            if let Some(byte) = line.get(i) {
+                Config::LOWER.encode(*byte, true, &mut out[len..]);
+            }
+            len += if i == LINE_LEN / 2 - 1 { 4 } else { 3 };
+        }
+        len += 1;
+
+        out[len] = b'|';
+        len += 1;
+        for byte in line.iter() {
+            out[len] = if byte.is_ascii_graphic() || *byte == b' ' { *byte } else { b'.' };
+            len += 1;
+        }
+        out[len] = b'|';
+        out[len + 1] = b'\n';
+        len += 2;
+
+        for c in out[..len].iter() {
+            self.inner.consume(*c)?;
+        }
+
+        self.offset += self.line_len as u64;
+        self.line_len = 0;
+        Ok(())
+    }
+}
+
+impl<C> Wrapper<C> for Hexdump<C> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C> AsRef<C> for Hexdump<C> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C> AsMut<C> for Hexdump<C> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: Consumer<Item = u8>> Consumer for Hexdump<C> {
+    type Item = u8;
+    type Error = C::Error;
+
+    fn consume(&mut self, item: u8) -> Result<(), Self::Error> {
+        if self.line_len == LINE_LEN {
+            self.write_line()?;
+        }
+
+        self.line[self.line_len] = MaybeUninit::new(item);
+        self.line_len += 1;
+        if self.line_len == LINE_LEN {
+            self.write_line()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.line_len > 0 {
+            self.write_line()?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<C: Consumer<Item = u8>> BulkConsumer for Hexdump<C> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        if self.line_len == LINE_LEN {
+            self.write_line()?;
+        }
+
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(&mut self.line[self.line_len..]) })
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        // A complete line is written by the next call.
+        self.line_len += amount.get();
+    }
+}
+
+/// Consumes bytes by writing them into a `core::fmt::Write`, as the `char`s of the same value. This
+/// writes ASCII text such as a `Hexdump` faithfully.
+pub struct FmtWrite<W>(W);
+
+impl<W> Wrapper<W> for FmtWrite<W> {
+    fn into_inner(self) -> W {
+        self.0
+    }
+}
+
+impl<W> AsRef<W> for FmtWrite<W> {
+    fn as_ref(&self) -> &W {
+        &self.0
+    }
+}
+
+impl<W> AsMut<W> for FmtWrite<W> {
+    fn as_mut(&mut self) -> &mut W {
+        &mut self.0
+    }
+}
+
+impl<W: fmt::Write> Consumer for FmtWrite<W> {
+    type Item = u8;
+    type Error = fmt::Error;
+
+    fn consume(&mut self, item: u8) -> Result<(), Self::Error> {
+        self.0.write_char(item as char)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,636 @@
+//! A streaming JSON lexer, as specified in RFC 8259.
+//!
+//! A `Lexer` is a `Producer` of the `JsonToken`s of the JSON text that a wrapped `BulkProducer`
+//! produces. It checks that the tokens form valid JSON, but never holds more than a single chunk
+//! of a string or a single number: nesting is tracked in a fixed-size stack, and strings are
+//! passed on in chunks, with all escape sequences resolved.
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::num::NonZeroUsize;
+
+use wrapper::Wrapper;
+
+use crate::*;
+use crate::utf8::Utf8Validator;
+
+/// The largest nesting depth that a lexer supports, regardless of its config.
+pub const MAX_DEPTH: usize = 1024;
+
+/// Configures a `Lexer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    /// The maximal number of arrays and objects that may be nested within each other. Values
+    /// larger than `MAX_DEPTH` act as `MAX_DEPTH`.
+    pub max_depth: usize,
+}
+
+impl Config {
+    /// Allows a nesting depth of 128.
+    pub const DEFAULT: Config = Config { max_depth: 128 };
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::DEFAULT
+    }
+}
+
+/// A token of a JSON text, as produced by a `Lexer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JsonToken {
+    /// `{`
+    BeginObject,
+    /// `}`
+    EndObject,
+    /// `[`
+    BeginArray,
+    /// `]`
+    EndArray,
+    /// The start of a string that is the key of an object member. Its contents follow as `Chunk`s,
+    /// up to a `StringEnd`.
+    Key,
+    /// The start of a string value. Its contents follow as `Chunk`s, up to a `StringEnd`.
+    String,
+    /// A nonempty chunk of the contents of a string, of the given length in bytes, available from
+    /// `Lexer::chunk`. Escape sequences have been resolved. A chunk may end within a code point,
+    /// only the whole string is guaranteed to be valid UTF-8.
+    Chunk(usize),
+    /// The end of a string.
+    StringEnd,
+    /// A number, whose text of the given length is available from `Lexer::chunk`.
+    Number(usize),
+    /// `false` or `true`.
+    Bool(bool),
+    /// `null`
+    Null,
+}
+
+/// Emitted by a `Lexer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LexError<E> {
+    /// The offset in the input of the byte that caused the error, or the length of the input if
+    /// the error occurred at its end.
+    pub offset: u64,
+    pub kind: LexErrorKind<E>,
+}
+
+/// The different errors of a `Lexer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LexErrorKind<E> {
+    /// The wrapped producer emitted an error between two values at the top level, e.g. because
+    /// the input has ended.
+    Inner(E),
+    /// A byte that is not allowed at its position, e.g. a missing comma or an unescaped control
+    /// character within a string.
+    UnexpectedByte(u8),
+    /// An unknown escape sequence, or an escaped surrogate that is not part of a pair.
+    InvalidEscape,
+    /// A string that is not valid UTF-8.
+    InvalidUtf8,
+    /// Arrays and objects are nested deeper than the config allows.
+    TooDeep,
+    /// A number that does not fit into the buffer of the lexer.
+    NumberTooLong,
+    /// The input ended within a value.
+    Incomplete,
+}
+
+// The states of the number grammar, named after the last part that was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberState {
+    Minus,
+    Zero,
+    Int,
+    Point,
+    Fraction,
+    E,
+    ExponentSign,
+    Exponent,
+}
+
+impl NumberState {
+    // Returns the state after the given byte, or `None` if the byte does not continue the number.
+    fn next(self, byte: u8) -> Option<NumberState> {
+        use NumberState::*;
+        Some(match (self, byte) {
+            (Minus, b'0') => Zero,
+            (Minus, b'1'..=b'9') | (Int, b'0'..=b'9') => Int,
+            (Zero, b'.') | (Int, b'.') => Point,
+            (Point, b'0'..=b'9') | (Fraction, b'0'..=b'9') => Fraction,
+            (Zero, b'e') | (Zero, b'E') | (Int, b'e') | (Int, b'E') | (Fraction, b'e') | (Fraction, b'E') => E,
+            (E, b'+') | (E, b'-') => ExponentSign,
+            (E, b'0'..=b'9') | (ExponentSign, b'0'..=b'9') | (Exponent, b'0'..=b'9') => Exponent,
+            _ => return None,
+        })
+    }
+
+    // Whether the number may end in this state.
+    fn is_complete(self) -> bool {
+        matches!(self, NumberState::Zero | NumberState::Int | NumberState::Fraction | NumberState::Exponent)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // expecting a value, or the end of the input at the top level
+    Value,
+    // after `[`, expecting a value or `]`
+    ArrayFirst,
+    // after `{`, expecting a key or `}`
+    ObjectFirst,
+    // after a `,` within an object, expecting a key
+    Key,
+    // after a key, expecting `:`
+    Colon,
+    // after a value, expecting a `,` or the end of the enclosing container
+    AfterValue,
+    String { key: bool },
+    // after a `\` within a string
+    Escape { key: bool },
+    // within a `\u` escape sequence, possibly the second one of a surrogate pair
+    Unicode { key: bool, digits: u8, value: u32, high: Option<u32> },
+    // after the escaped high surrogate of a pair, expecting `\u`
+    LowSurrogate { key: bool, high: u32, backslash: bool },
+    Number(NumberState),
+    // within `true`, `false` or `null`
+    Literal { text: &'static [u8], read: usize },
+}
+
+// Whether a step consumed the byte, and the token it completed.
+enum Step {
+    Consumed(Option<JsonToken>),
+    // The byte has not been consumed, it is processed again by the next step.
+    Kept(JsonToken),
+}
+
+struct LexState {
+    state: State,
+    max_depth: usize,
+    // one bit per enclosing container, set for objects
+    nesting: [u64; MAX_DEPTH / 64],
+    depth: usize,
+    utf8: Utf8Validator,
+    // the number of bytes of the current chunk or number in the buffer
+    len: usize,
+    // the length of the chunk or number of the most recently produced token
+    chunk_len: usize,
+    // a token to produce before reading further input
+    pending: Option<JsonToken>,
+    // whether the previous value was a number or a literal that no whitespace has followed yet,
+    // so that a number or literal cannot follow at the top level
+    needs_space: bool,
+    // the number of bytes read so far
+    offset: u64,
+}
+
+impl LexState {
+    fn error<E>(&self, kind: LexErrorKind<E>) -> LexError<E> {
+        LexError { offset: self.offset, kind }
+    }
+
+    fn in_string(&self) -> bool {
+        matches!(self.state, State::String { .. } | State::Escape { .. } | State::Unicode { .. } | State::LowSurrogate { .. })
+    }
+
+    fn in_object(&self) -> bool {
+        self.depth > 0 && (self.nesting[(self.depth - 1) / 64] >> ((self.depth - 1) % 64)) & 1 == 1
+    }
+
+    fn open<E>(&mut self, object: bool) -> Result<Step, LexError<E>> {
+        if self.depth == self.max_depth {
+            return Err(self.error(LexErrorKind::TooDeep));
+        }
+
+        let (word, bit) = (self.depth / 64, self.depth % 64);
+        self.nesting[word] = (self.nesting[word] & !(1 << bit)) | ((object as u64) << bit);
+        self.depth += 1;
+        if object {
+            self.state = State::ObjectFirst;
+            Ok(Step::Consumed(Some(JsonToken::BeginObject)))
+        } else {
+            self.state = State::ArrayFirst;
+            Ok(Step::Consumed(Some(JsonToken::BeginArray)))
+        }
+    }
+
+    fn close<E>(&mut self, object: bool, byte: u8) -> Result<Step, LexError<E>> {
+        if self.depth == 0 || self.in_object() != object {
+            return Err(self.error(LexErrorKind::UnexpectedByte(byte)));
+        }
+
+        self.depth -= 1;
+        self.state = State::AfterValue;
+        self.needs_space = false;
+        Ok(Step::Consumed(Some(if object { JsonToken::EndObject } else { JsonToken::EndArray })))
+    }
+
+    fn start_string(&mut self, key: bool) -> Step {
+        self.state = State::String { key };
+        self.utf8 = Utf8Validator::new();
+        Step::Consumed(Some(if key { JsonToken::Key } else { JsonToken::String }))
+    }
+
+    // Hands out the current chunk.
+    fn take_chunk(&mut self) -> JsonToken {
+        self.chunk_len = self.len;
+        self.len = 0;
+        JsonToken::Chunk(self.chunk_len)
+    }
+
+    // Writes an escaped code point into the buffer.
+    fn push_code_point<E>(&mut self, buffer: &mut [u8], key: bool, code_point: u32) -> Result<Step, LexError<E>> {
+        match core::char::from_u32(code_point) {
+            Some(c) => {
+                self.len += c.encode_utf8(&mut buffer[self.len..]).len();
+                self.state = State::String { key };
+                Ok(Step::Consumed(None))
+            }
+            None => Err(self.error(LexErrorKind::InvalidEscape)),
+        }
+    }
+
+    fn start_value<E>(&mut self, buffer: &mut [u8], byte: u8) -> Result<Step, LexError<E>> {
+        match byte {
+            b'{' => self.open(true),
+            b'[' => self.open(false),
+            b'"' => Ok(self.start_string(false)),
+            b'-' | b'0'..=b'9' => {
+                buffer[0] = byte;
+                self.len = 1;
+                self.state = State::Number(match byte {
+                    b'-' => NumberState::Minus,
+                    b'0' => NumberState::Zero,
+                    _ => NumberState::Int,
+                });
+                Ok(Step::Consumed(None))
+            }
+            b't' | b'f' | b'n' => {
+                let text: &'static [u8] = match byte {
+                    b't' => b"true",
+                    b'f' => b"false",
+                    _ => b"null",
+                };
+                self.state = State::Literal { text, read: 1 };
+                Ok(Step::Consumed(None))
+            }
+            _ => Err(self.error(LexErrorKind::UnexpectedByte(byte))),
+        }
+    }
+
+    // Processes a single byte.
+    fn step<E>(&mut self, buffer: &mut [u8], byte: u8) -> Result<Step, LexError<E>> {
+        let whitespace = matches!(byte, b' ' | b'\t' | b'\n' | b'\r');
+        match self.state {
+            State::Value | State::ArrayFirst | State::ObjectFirst | State::Key | State::Colon | State::AfterValue if whitespace => {
+                self.needs_space = false;
+                Ok(Step::Consumed(None))
+            }
+            State::Value => self.start_value(buffer, byte),
+            State::ArrayFirst => match byte {
+                b']' => self.close(false, byte),
+                _ => self.start_value(buffer, byte),
+            },
+            State::ObjectFirst | State::Key => match byte {
+                b'"' => Ok(self.start_string(true)),
+                b'}' if self.state == State::ObjectFirst => self.close(true, byte),
+                _ => Err(self.error(LexErrorKind::UnexpectedByte(byte))),
+            },
+            State::Colon => match byte {
+                b':' => {
+                    self.state = State::Value;
+                    Ok(Step::Consumed(None))
+                }
+                _ => Err(self.error(LexErrorKind::UnexpectedByte(byte))),
+            },
+            // At the top level, values simply follow each other, but e.g. `12` must not be lexed
+            // as two numbers.
+            State::AfterValue if self.depth == 0 => match byte {
+                b'-' | b'0'..=b'9' | b't' | b'f' | b'n' if self.needs_space => {
+                    Err(self.error(LexErrorKind::UnexpectedByte(byte)))
+                }
+                _ => {
+                    self.needs_space = false;
+                    self.start_value(buffer, byte)
+                }
+            },
+            State::AfterValue => match byte {
+                b',' => {
+                    self.state = if self.in_object() { State::Key } else { State::Value };
+                    Ok(Step::Consumed(None))
+                }
+                b'}' => self.close(true, byte),
+                b']' => self.close(false, byte),
+                _ => Err(self.error(LexErrorKind::UnexpectedByte(byte))),
+            },
+            State::String { key } => {
+                if !self.utf8.is_complete() || byte >= 0x80 {
+                    if !self.utf8.push(byte) {
+                        return Err(self.error(LexErrorKind::InvalidUtf8));
+                    }
+                    buffer[self.len] = byte;
+                    self.len += 1;
+                    return Ok(Step::Consumed(None));
+                }
+
+                match byte {
+                    b'"' => {
+                        self.state = if key { State::Colon } else { State::AfterValue };
+                        if self.len > 0 {
+                            self.pending = Some(JsonToken::StringEnd);
+                            Ok(Step::Consumed(Some(self.take_chunk())))
+                        } else {
+                            Ok(Step::Consumed(Some(JsonToken::StringEnd)))
+                        }
+                    }
+                    b'\\' => {
+                        self.state = State::Escape { key };
+                        Ok(Step::Consumed(None))
+                    }
+                    0x00..=0x1f => Err(self.error(LexErrorKind::UnexpectedByte(byte))),
+                    _ => {
+                        buffer[self.len] = byte;
+                        self.len += 1;
+                        Ok(Step::Consumed(None))
+                    }
+                }
+            }
+            State::Escape { key } => {
+                let unescaped = match byte {
+                    b'"' | b'\\' | b'/' => byte,
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'u' => {
+                        self.state = State::Unicode { key, digits: 0, value: 0, high: None };
+                        return Ok(Step::Consumed(None));
+                    }
+                    _ => return Err(self.error(LexErrorKind::InvalidEscape)),
+                };
+                buffer[self.len] = unescaped;
+                self.len += 1;
+                self.state = State::String { key };
+                Ok(Step::Consumed(None))
+            }
+            State::Unicode { key, digits, value, high } => {
+                let digit = match (byte as char).to_digit(16) {
+                    Some(digit) => digit,
+                    None => return Err(self.error(LexErrorKind::InvalidEscape)),
+                };
+                let value = (value << 4) | digit;
+                if digits < 3 {
+                    self.state = State::Unicode { key, digits: digits + 1, value, high };
+                    return Ok(Step::Consumed(None));
+                }
+
+                match (high, value) {
+                    (None, 0xd800..=0xdbff) => {
+                        self.state = State::LowSurrogate { key, high: value, backslash: false };
+                        Ok(Step::Consumed(None))
+                    }
+                    (Some(high), 0xdc00..=0xdfff) => {
+                        self.push_code_point(buffer, key, 0x10000 + ((high - 0xd800) << 10) + (value - 0xdc00))
+                    }
+                    (None, _) => self.push_code_point(buffer, key, value),
+                    (Some(_), _) => Err(self.error(LexErrorKind::InvalidEscape)),
+                }
+            }
+            State::LowSurrogate { key, high, backslash } => match (byte, backslash) {
+                (b'\\', false) => {
+                    self.state = State::LowSurrogate { key, high, backslash: true };
+                    Ok(Step::Consumed(None))
+                }
+                (b'u', true) => {
+                    self.state = State::Unicode { key, digits: 0, value: 0, high: Some(high) };
+                    Ok(Step::Consumed(None))
+                }
+                _ => Err(self.error(LexErrorKind::InvalidEscape)),
+            },
+            State::Number(number) => match number.next(byte) {
+                Some(next) => {
+                    if self.len == buffer.len() {
+                        return Err(self.error(LexErrorKind::NumberTooLong));
+                    }
+                    buffer[self.len] = byte;
+                    self.len += 1;
+                    self.state = State::Number(next);
+                    Ok(Step::Consumed(None))
+                }
+                None if number.is_complete() => Ok(Step::Kept(self.end_number())),
+                None => Err(self.error(LexErrorKind::UnexpectedByte(byte))),
+            },
+            State::Literal { text, read } => {
+                if byte != text[read] {
+                    return Err(self.error(LexErrorKind::UnexpectedByte(byte)));
+                } else if read + 1 < text.len() {
+                    self.state = State::Literal { text, read: read + 1 };
+                    return Ok(Step::Consumed(None));
+                }
+
+                self.state = State::AfterValue;
+                self.needs_space = true;
+                Ok(Step::Consumed(Some(match text[0] {
+                    b't' => JsonToken::Bool(true),
+                    b'f' => JsonToken::Bool(false),
+                    _ => JsonToken::Null,
+                })))
+            }
+        }
+    }
+
+    // Returns the error to emit once the wrapped producer has emitted an error.
+    fn end<E>(&self, e: E) -> LexError<E> {
+        match self.state {
+            State::Value | State::AfterValue if self.depth == 0 => self.error(LexErrorKind::Inner(e)),
+            _ => self.error(LexErrorKind::Incomplete),
+        }
+    }
+
+    fn end_number(&mut self) -> JsonToken {
+        self.state = State::AfterValue;
+        self.needs_space = true;
+        self.chunk_len = self.len;
+        self.len = 0;
+        JsonToken::Number(self.chunk_len)
+    }
+
+    // Processes the given bytes until a token is complete. Returns the number of bytes read, and
+    // the token.
+    fn lex<E>(&mut self, buffer: &mut [u8], bytes: &[u8]) -> Result<(usize, Option<JsonToken>), LexError<E>> {
+        let mut read = 0;
+        while read < bytes.len() {
+            if self.in_string() {
+                // Make sure that any escape sequence fits into the buffer.
+                let free = buffer.len() - self.len;
+                if free < 4 {
+                    return Ok((read, Some(self.take_chunk())));
+                }
+
+                if let (State::String { .. }, true) = (self.state, self.utf8.is_complete()) {
+                    // Copy all plain ASCII characters at once.
+                    let available = &bytes[read..min(bytes.len(), read + free)];
+                    let run = available.iter()
+                        .position(|byte| *byte < 0x20 || *byte >= 0x80 || *byte == b'"' || *byte == b'\\')
+                        .unwrap_or(available.len());
+                    if run > 0 {
+                        buffer[self.len..self.len + run].copy_from_slice(&available[..run]);
+                        self.len += run;
+                        self.offset += run as u64;
+                        read += run;
+                        continue;
+                    }
+                }
+            }
+
+            match self.step(buffer, bytes[read])? {
+                Step::Consumed(token) => {
+                    read += 1;
+                    self.offset += 1;
+                    if token.is_some() {
+                        return Ok((read, token));
+                    }
+                }
+                Step::Kept(token) => return Ok((read, Some(token))),
+            }
+        }
+
+        // Chunks of strings do not extend beyond the slots of the wrapped producer.
+        if self.in_string() && self.len > 0 {
+            Ok((read, Some(self.take_chunk())))
+        } else {
+            Ok((read, None))
+        }
+    }
+}
+
+/// Creates a lexer that produces the tokens of the JSON text that `inner` produces, using
+/// `buffer` for the chunks of strings and the text of numbers. Panics if the buffer is shorter
+/// than four bytes, the length of the longest UTF-8 encoding of a code point.
+pub fn lexer<P: Producer, B: AsMut<[u8]>>(inner: P, mut buffer: B, config: Config) -> Lexer<P, B> {
+    assert!(buffer.as_mut().len() >= 4, "a JSON lexer needs a buffer of at least four bytes");
+    Lexer {
+        inner,
+        buffer,
+        state: LexState {
+            state: State::Value,
+            max_depth: min(config.max_depth, MAX_DEPTH),
+            nesting: [0; MAX_DEPTH / 64],
+            depth: 0,
+            utf8: Utf8Validator::new(),
+            len: 0,
+            chunk_len: 0,
+            pending: None,
+            needs_space: false,
+            offset: 0,
+        },
+        err: None,
+    }
+}
+
+/// Produces the `JsonToken`s of the JSON text of a wrapped `BulkProducer`.
+///
+/// The input may consist of any number of JSON values, so that e.g. JSON Lines can be lexed as
+/// well. Numbers and literals must be separated from each other by whitespace, other values may
+/// follow each other directly. It ends when the wrapped producer emits an error.
+///
+/// The chunks of strings and the text of numbers are copied into the buffer of the lexer, which
+/// can hold any type that can be viewed as a mutable slice of bytes. The buffer bounds the length
+/// of chunks and numbers. The bytes of a `Chunk` or `Number` token are available from `chunk`
+/// until the next call to `produce`.
+pub struct Lexer<P: Producer, B> {
+    inner: P,
+    buffer: B,
+    state: LexState,
+    // the error that ended the input, emitted after the number that it ended
+    err: Option<P::Error>,
+}
+
+impl<P: Producer, B> Lexer<P, B> {
+    /// Returns the number of bytes of the input that have been read so far.
+    pub fn offset(&self) -> u64 {
+        self.state.offset
+    }
+
+    /// Returns the current nesting depth, i.e. the number of arrays and objects that have been
+    /// begun but not ended.
+    pub fn depth(&self) -> usize {
+        self.state.depth
+    }
+}
+
+impl<P: Producer, B: AsRef<[u8]>> Lexer<P, B> {
+    /// Returns the bytes of the most recently produced `Chunk` or `Number` token, or an empty
+    /// slice after any other token.
+    pub fn chunk(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.state.chunk_len]
+    }
+}
+
+impl<P: Producer, B> Wrapper<P> for Lexer<P, B> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Producer, B> AsRef<P> for Lexer<P, B> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P: Producer, B> AsMut<P> for Lexer<P, B> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]>> Producer for Lexer<P, B> {
+    type Item = JsonToken;
+    type Error = LexError<P::Error>;
+
+    fn produce(&mut self) -> Result<JsonToken, Self::Error> {
+        if let Some(token) = self.state.pending.take() {
+            self.state.chunk_len = 0;
+            return Ok(token);
+        } else if let Some(e) = self.err.take() {
+            self.state.chunk_len = 0;
+            return Err(self.state.end(e));
+        }
+
+        self.state.chunk_len = 0;
+        loop {
+            let bytes = match self.inner.producer_slots() {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return match self.state.state {
+                        State::Number(number) if number.is_complete() => {
+                            self.err = Some(e);
+                            Ok(self.state.end_number())
+                        }
+                        _ => Err(self.state.end(e)),
+                    };
+                }
+            };
+
+            let (read, token) = self.state.lex(self.buffer.as_mut(), bytes)?;
+            if let Some(amount) = NonZeroUsize::new(read) {
+                self.inner.did_produce(amount);
+            }
+
+            if let Some(token) = token {
+                return Ok(token);
+            }
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        let offset = self.state.offset;
+        self.inner.slurp().map_err(|e| LexError { offset, kind: LexErrorKind::Inner(e) })
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let pending = self.state.pending.is_some() as usize;
+        (pending, self.inner.remaining_hint().1.and_then(|upper| upper.checked_add(pending)))
+    }
+}
@@ -0,0 +1,383 @@
+//! Streaming LEB128 encoding and decoding of 64 bit integers.
+//!
+//! An `Encoder` is a `BulkProducer` of the bytes that encode the integers of a wrapped `Producer`,
+//! a `Decoder` is a `Producer` of the integers encoded by the bytes of a wrapped `BulkProducer`.
+//! The encoding of an integer may be split arbitrarily across the slots of the wrapped producer.
+//!
+//! There are three variants: `Unsigned` encodes `u64`s, `Signed` encodes `i64`s in two's
+//! complement, and `ZigZag` maps `i64`s of small magnitude to small `u64`s before encoding them as
+//! `Unsigned` does.
+use core::convert::{AsRef, AsMut};
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::*;
+
+/// The maximal number of bytes in the encoding of a 64 bit integer.
+pub const MAX_LEN: usize = 10;
+
+/// A variant of LEB128, determining which integers are encoded and how.
+pub trait Variant {
+    /// The type of the encoded integers.
+    type Int: Copy;
+    /// Whether the 64 bits of an integer are encoded with sign extension, as signed LEB128 does.
+    const SIGNED: bool;
+
+    /// Converts an integer into the 64 bits to encode.
+    fn to_bits(int: Self::Int) -> u64;
+
+    /// Converts 64 decoded bits into an integer.
+    fn from_bits(bits: u64) -> Self::Int;
+}
+
+/// Unsigned LEB128, which encodes `u64`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Unsigned;
+
+impl Variant for Unsigned {
+    type Int = u64;
+    const SIGNED: bool = false;
+
+    fn to_bits(int: u64) -> u64 {
+        int
+    }
+
+    fn from_bits(bits: u64) -> u64 {
+        bits
+    }
+}
+
+/// Signed LEB128, which encodes `i64`s in two's complement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Signed;
+
+impl Variant for Signed {
+    type Int = i64;
+    const SIGNED: bool = true;
+
+    fn to_bits(int: i64) -> u64 {
+        int as u64
+    }
+
+    fn from_bits(bits: u64) -> i64 {
+        bits as i64
+    }
+}
+
+/// Encodes `i64`s as unsigned LEB128, after mapping `0, -1, 1, -2, 2, ...` to `0, 1, 2, 3, 4, ...`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ZigZag;
+
+impl Variant for ZigZag {
+    type Int = i64;
+    const SIGNED: bool = false;
+
+    fn to_bits(int: i64) -> u64 {
+        ((int << 1) ^ (int >> 63)) as u64
+    }
+
+    fn from_bits(bits: u64) -> i64 {
+        ((bits >> 1) as i64) ^ -((bits & 1) as i64)
+    }
+}
+
+// Encodes the bits into `out`, returns the number of bytes.
+fn encode(bits: u64, signed: bool, out: &mut [u8; MAX_LEN]) -> usize {
+    let mut bits = bits;
+    let mut len = 0;
+    loop {
+        let byte = (bits & 0x7f) as u8;
+        bits = if signed { ((bits as i64) >> 7) as u64 } else { bits >> 7 };
+
+        let done = if signed {
+            (bits == 0 && byte & 0x40 == 0) || (bits == u64::MAX && byte & 0x40 != 0)
+        } else {
+            bits == 0
+        };
+
+        if done {
+            out[len] = byte;
+            return len + 1;
+        } else {
+            out[len] = byte | 0x80;
+            len += 1;
+        }
+    }
+}
+
+/// Creates an encoder that produces the LEB128 encoding of the integers that `inner` produces.
+pub fn encoder<P, V>(inner: P, variant: V) -> Encoder<P, V> {
+    Encoder {
+        inner,
+        variant,
+        encoded: [0; MAX_LEN],
+        encoded_start: 0,
+        encoded_end: 0,
+    }
+}
+
+/// Produces the bytes of the LEB128 encoding of the integers of a wrapped `Producer`.
+pub struct Encoder<P, V> {
+    inner: P,
+    variant: V,
+    // the encoding of the current integer, of which the bytes up to `encoded_start` have been
+    // produced already
+    encoded: [u8; MAX_LEN],
+    encoded_start: usize,
+    encoded_end: usize,
+}
+
+impl<P, V> Encoder<P, V> {
+    /// Returns the variant of LEB128 that is produced.
+    pub fn variant(&self) -> &V {
+        &self.variant
+    }
+}
+
+impl<P: Producer<Item = V::Int>, V: Variant> Encoder<P, V> {
+    // Encodes the next integer, if all bytes of the current one have been produced.
+    fn fill(&mut self) -> Result<(), P::Error> {
+        if self.encoded_start == self.encoded_end {
+            let int = self.inner.produce()?;
+            self.encoded_end = encode(V::to_bits(int), V::SIGNED, &mut self.encoded);
+            self.encoded_start = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<P, V> Wrapper<P> for Encoder<P, V> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P, V> AsRef<P> for Encoder<P, V> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, V> AsMut<P> for Encoder<P, V> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: Producer<Item = V::Int>, V: Variant> Producer for Encoder<P, V> {
+    type Item = u8;
+    type Error = P::Error;
+
+    fn produce(&mut self) -> Result<u8, Self::Error> {
+        self.fill()?;
+        let item = self.encoded[self.encoded_start];
+        self.encoded_start += 1;
+        Ok(item)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp()
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let encoded = self.encoded_end - self.encoded_start;
+        let (lower, upper) = self.inner.remaining_hint();
+        (
+            encoded.saturating_add(lower),
+            upper.and_then(|ints| ints.checked_mul(MAX_LEN)).and_then(|bytes| bytes.checked_add(encoded)),
+        )
+    }
+}
+
+impl<P: Producer<Item = V::Int>, V: Variant> BulkProducer for Encoder<P, V> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        self.fill()?;
+        Ok(unsafe { Slice1::from_slice_unchecked(&self.encoded[self.encoded_start..self.encoded_end]) })
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.encoded_start += amount.get();
+    }
+}
+
+/// Emitted by a `Decoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError<E> {
+    /// The wrapped producer emitted an error, e.g. because the input has ended. All integers
+    /// encoded by the input have been produced before.
+    Inner(E),
+    /// An encoding has more bytes than necessary, e.g. `0x80 0x00` instead of `0x00`.
+    Overlong,
+    /// An encoding encodes a value that does not fit into 64 bits.
+    Overflow,
+    /// The input ended within the encoding of an integer.
+    Incomplete,
+}
+
+// The progress of decoding a single integer.
+struct DecodeState {
+    // the bits decoded so far
+    bits: u64,
+    // the number of bits decoded so far
+    shift: u32,
+    // the previous byte of the current encoding
+    previous: u8,
+    // whether the remaining bytes of an overflowing encoding are skipped
+    skip: bool,
+}
+
+impl DecodeState {
+    // Decodes the next byte, returns the bits of the integer if the byte was the last one of its
+    // encoding.
+    fn push<E>(&mut self, byte: u8, signed: bool) -> Result<Option<u64>, DecodeError<E>> {
+        if self.skip {
+            self.skip = byte & 0x80 != 0;
+            return Ok(None);
+        }
+
+        let low = byte & 0x7f;
+        if self.shift == 63 {
+            // Only a single bit is left, the remaining bits must be (sign) extensions of it.
+            let fits = if signed { low == 0 || low == 0x7f } else { low <= 1 };
+            if !fits || byte & 0x80 != 0 {
+                self.reset();
+                self.skip = byte & 0x80 != 0;
+                return Err(DecodeError::Overflow);
+            }
+        }
+        self.bits |= (low as u64) << self.shift;
+
+        if byte & 0x80 != 0 {
+            self.shift += 7;
+            self.previous = byte;
+            return Ok(None);
+        }
+
+        let overlong = self.shift > 0 && if signed {
+            (byte == 0 && self.previous & 0x40 == 0) || (byte == 0x7f && self.previous & 0x40 != 0)
+        } else {
+            byte == 0
+        };
+
+        let mut bits = self.bits;
+        if signed && self.shift < 57 && byte & 0x40 != 0 {
+            bits |= u64::MAX << (self.shift + 7);
+        }
+        self.reset();
+
+        if overlong {
+            Err(DecodeError::Overlong)
+        } else {
+            Ok(Some(bits))
+        }
+    }
+
+    fn reset(&mut self) {
+        self.bits = 0;
+        self.shift = 0;
+        self.previous = 0;
+        self.skip = false;
+    }
+}
+
+/// Creates a decoder that produces the integers whose LEB128 encodings `inner` produces.
+pub fn decoder<P, V>(inner: P, variant: V) -> Decoder<P, V> {
+    Decoder {
+        inner,
+        variant,
+        state: DecodeState {
+            bits: 0,
+            shift: 0,
+            previous: 0,
+            skip: false,
+        },
+    }
+}
+
+/// Produces the integers encoded by the LEB128 bytes of a wrapped `BulkProducer`. The input ends
+/// when the wrapped producer emits an error.
+///
+/// After an `Overlong` error, decoding resumes with the byte after the offending encoding. After
+/// an `Overflow` error, the remaining bytes of the offending encoding are skipped, up to and
+/// including the next byte without continuation bit.
+pub struct Decoder<P, V> {
+    inner: P,
+    variant: V,
+    state: DecodeState,
+}
+
+impl<P, V> Decoder<P, V> {
+    /// Returns the variant of LEB128 that is decoded.
+    pub fn variant(&self) -> &V {
+        &self.variant
+    }
+}
+
+impl<P, V> Wrapper<P> for Decoder<P, V> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P, V> AsRef<P> for Decoder<P, V> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, V> AsMut<P> for Decoder<P, V> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, V: Variant> Producer for Decoder<P, V> {
+    type Item = V::Int;
+    type Error = DecodeError<P::Error>;
+
+    fn produce(&mut self) -> Result<V::Int, Self::Error> {
+        loop {
+            let bytes = match self.inner.producer_slots() {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return if self.state.shift > 0 || self.state.skip {
+                        self.state.reset();
+                        Err(DecodeError::Incomplete)
+                    } else {
+                        Err(DecodeError::Inner(e))
+                    };
+                }
+            };
+
+            let mut amount = bytes.len_();
+            let mut decoded = None;
+            for (i, byte) in bytes.iter().enumerate() {
+                match self.state.push(*byte, V::SIGNED) {
+                    Ok(None) => {}
+                    Ok(Some(bits)) => decoded = Some(Ok(V::from_bits(bits))),
+                    Err(e) => decoded = Some(Err(e)),
+                }
+
+                if decoded.is_some() {
+                    amount = i + 1;
+                    break;
+                }
+            }
+
+            self.inner.did_produce(unsafe { NonZeroUsize::new_unchecked(amount) });
+            if let Some(decoded) = decoded {
+                return decoded;
+            }
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(DecodeError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.remaining_hint().1)
+    }
+}
@@ -1,11 +1,22 @@
-#![cfg_attr(not(feature = "std"), feature(no_std))]
-#![feature(maybe_uninit_write_slice)]
-#![feature(maybe_uninit_slice)]
-#![feature(maybe_uninit_extra)]
-#![feature(maybe_uninit_uninit_array)]
-#![cfg_attr(all(any(feature = "alloc", feature = "std"), feature = "arbitrary"), feature(new_uninit))]
-#![cfg_attr(any(feature = "alloc", feature = "std"), feature(allocator_api))]
-#![feature(never_type)]
+// The `arbitrary` crate and its derive macros require the standard library.
+#![cfg_attr(not(any(feature = "std", feature = "arbitrary")), no_std)]
+#![cfg_attr(feature = "unstable", feature(maybe_uninit_write_slice))]
+#![cfg_attr(feature = "unstable", feature(maybe_uninit_slice))]
+#![cfg_attr(feature = "unstable", feature(maybe_uninit_extra))]
+#![cfg_attr(feature = "unstable", feature(maybe_uninit_uninit_array))]
+#![cfg_attr(all(feature = "unstable", any(feature = "alloc", feature = "std")), feature(new_uninit))]
+#![cfg_attr(all(feature = "unstable", any(feature = "alloc", feature = "std")), feature(allocator_api))]
+
+//! Without the `unstable` feature, this crate builds on stable Rust. The `unstable` feature
+//! enables the allocator-generic constructors (which require the nightly `allocator_api`), and
+//! uses the nightly `MaybeUninit` slice methods internally.
+//!
+//! The crate is `no_std` unless the `std` or the `arbitrary` feature is enabled. The `arbitrary`
+//! feature is enabled by default and links the standard library, so `no_std` users need to
+//! disable the default features.
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod allocator;
 
 #[cfg(any(feature = "alloc", feature = "std"))]
 mod ringbuffer;
@@ -20,6 +31,49 @@ pub mod con {
 }
 use con::{Consumer, BulkConsumer};
 
+pub mod spsc;
+
+pub mod time;
+use time::{Clock, TimeoutError};
+
+pub mod base64;
+
+pub mod hex;
+
+pub mod leb128;
+
+pub mod cobs;
+
+pub mod slip;
+
+mod frame;
+
+pub mod cbor;
+
+pub mod json;
+
+pub mod csv;
+
+mod utf8;
+
+#[cfg(feature = "alloc")]
+pub mod testing;
+
+#[cfg(feature = "alloc")]
+mod prng;
+
+#[cfg(feature = "std")]
+pub mod mpsc;
+
+#[cfg(feature = "std")]
+pub mod broadcast;
+
+#[cfg(feature = "std")]
+mod spawn_pipe;
+#[cfg(feature = "std")]
+pub use spawn_pipe::*;
+
+use core::cmp::min;
 use core::mem::MaybeUninit;
 
 pub(crate) fn maybe_uninit_slice<'a, T>(s: &'a [T]) -> &'a [MaybeUninit<T>] {
@@ -32,11 +86,56 @@ pub(crate) fn maybe_uninit_slice_mut<'a, T>(s: &'a mut [T]) -> &'a mut [MaybeUni
     unsafe { core::slice::from_raw_parts_mut(ptr, s.len()) }
 }
 
+/// Copies `src` into `dst`, which must have the same length.
+pub(crate) fn write_slice<T: Copy>(dst: &mut [MaybeUninit<T>], src: &[T]) {
+    #[cfg(feature = "unstable")]
+    MaybeUninit::write_slice(dst, src);
+    #[cfg(not(feature = "unstable"))]
+    dst.copy_from_slice(maybe_uninit_slice(src));
+}
+
+/// Consumes all items of `data`, in as few calls to `bulk_consume` as the consumer allows.
+pub(crate) fn consume_slice<C, T>(c: &mut C, data: &[T]) -> Result<(), C::Error> where
+    T: Copy,
+    C: BulkConsumer<Item = T>,
+{
+    let mut position = 0;
+    while let Some(s) = slice_n::Slice1::from_slice(&data[position..]) {
+        position += c.bulk_consume(s)?.get();
+    }
+    Ok(())
+}
+
+/// Safety: all items in the slice must be initialized.
+pub(crate) unsafe fn slice_assume_init_ref<'a, T>(s: &'a [MaybeUninit<T>]) -> &'a [T] {
+    #[cfg(feature = "unstable")]
+    return MaybeUninit::slice_assume_init_ref(s);
+    #[cfg(not(feature = "unstable"))]
+    return core::slice::from_raw_parts(s.as_ptr().cast::<T>(), s.len());
+}
+
+/// The error type of operations that cannot fail. This is `core::convert::Infallible` regardless
+/// of the enabled features.
+pub type Infallible = core::convert::Infallible;
+
+// The most items the pipe functions reserve for up front, so that a wrong hint cannot make them
+// allocate absurd amounts of memory.
+const MAX_RESERVE: usize = 1 << 20;
+
+// Lets the consumer reserve space for the items of the producer, if the producer is known to be
+// finite.
+fn reserve_for<P: Producer, C: Consumer>(p: &P, c: &mut C) {
+    if let (lower, Some(upper)) = p.remaining_hint() {
+        c.reserve(min(min(lower, upper), MAX_RESERVE));
+    }
+}
+
 /// Pipes all items from the `Producer` into the `Consumer`. Does neither flush nor slurp.
 pub fn pipe<P, C, T, E>(p: &mut P, c: &mut C) -> E where
     P: Producer<Item = T, Error = E>,
     C: Consumer<Item = T, Error = E>,
 {
+    reserve_for(p, c);
     loop {
         match p.produce() {
             Ok(item) => match c.consume(item) {
@@ -48,12 +147,37 @@ pub fn pipe<P, C, T, E>(p: &mut P, c: &mut C) -> E where
     }
 }
 
+/// Pipes all items from the `Producer` into the `Consumer` like `pipe`, but stops with
+/// `TimeoutError::TimedOut` once the deadline has passed according to the clock. The deadline is
+/// checked before each item. Does neither flush nor slurp.
+pub fn pipe_until<P, C, T, E, K>(p: &mut P, c: &mut C, clock: &K, deadline: K::Instant) -> TimeoutError<E> where
+    P: Producer<Item = T, Error = E>,
+    C: Consumer<Item = T, Error = E>,
+    K: Clock,
+{
+    reserve_for(p, c);
+    loop {
+        if clock.now() >= deadline {
+            return TimeoutError::TimedOut;
+        }
+
+        match p.produce() {
+            Ok(item) => match c.consume(item) {
+                Ok(()) => {}
+                Err(e) => return TimeoutError::Inner(e),
+            }
+            Err(e) => return TimeoutError::Inner(e),
+        }
+    }
+}
+
 /// Writes all items from the `BulkProducer` to the `BulkConsumer`. Does neither flush nor slurp.
 pub fn bulk_produce_all<P, C, T, E>(p: &mut P, c: &mut C) -> E where
     T: Copy,
     P: BulkProducer<Item = T, Error = E>,
     C: BulkConsumer<Item = T, Error = E>,
 {
+    reserve_for(p, c);
     loop {
         match c.consumer_slots() {
             Ok(s) => match p.bulk_produce(s) {
@@ -71,6 +195,7 @@ pub fn bulk_consume_all<P, C, T, E>(p: &mut P, c: &mut C) -> E where
     P: BulkProducer<Item = T, Error = E>,
     C: BulkConsumer<Item = T, Error = E>,
 {
+    reserve_for(p, c);
     loop {
         match p.producer_slots() {
             Ok(s) => match c.bulk_consume(s) {
@@ -81,3 +206,66 @@ pub fn bulk_consume_all<P, C, T, E>(p: &mut P, c: &mut C) -> E where
         }
     }
 }
+
+/// Writes all items from the `BulkProducer` to the `BulkConsumer`, using the vectored slots of the
+/// `BulkConsumer`. Does neither flush nor slurp.
+pub fn bulk_produce_all_vectored<P, C, T, E>(p: &mut P, c: &mut C) -> E where
+    T: Copy,
+    P: BulkProducer<Item = T, Error = E>,
+    C: BulkConsumer<Item = T, Error = E>,
+{
+    reserve_for(p, c);
+    loop {
+        match c.consumer_slots_vectored() {
+            Ok((fst, snd)) => match p.bulk_produce_vectored(fst, snd) {
+                Ok(amount) => unsafe { c.did_consume(amount) },
+                Err(e) => return e,
+            }
+            Err(e) => return e,
+        }
+    }
+}
+
+/// Reads all items from the `BulkProducer` into the `BulkConsumer`, using the vectored slots of
+/// the `BulkProducer`. This drains e.g. a wrapped ring buffer into a `writev`-style
+/// `BulkConsumer` with a single call per round. Does neither flush nor slurp.
+pub fn bulk_consume_all_vectored<P, C, T, E>(p: &mut P, c: &mut C) -> E where
+    T: Copy,
+    P: BulkProducer<Item = T, Error = E>,
+    C: BulkConsumer<Item = T, Error = E>,
+{
+    reserve_for(p, c);
+    loop {
+        match p.producer_slots_vectored() {
+            Ok((fst, snd)) => match c.bulk_consume_vectored(fst, snd) {
+                Ok(amount) => p.did_produce(amount),
+                Err(e) => return e,
+            }
+            Err(e) => return e,
+        }
+    }
+}
+
+/// Moves all items from the `BulkProducer` into the `BulkConsumer`, picking between
+/// `bulk_produce_all` and `bulk_consume_all` based on the size hints of both sides. Does neither
+/// flush nor slurp.
+///
+/// A side whose hint is exact usually holds its items (or free slots) in memory already and can
+/// hand out large slices, so the items are moved through its slots.
+///
+/// `bulk_produce_all` and `bulk_consume_all` themselves keep their fixed direction, since callers
+/// may rely on whose slots are used, e.g. to have a consumer hand out memory-mapped slots.
+pub fn bulk_pipe<P, C, T, E>(p: &mut P, c: &mut C) -> E where
+    T: Copy,
+    P: BulkProducer<Item = T, Error = E>,
+    C: BulkConsumer<Item = T, Error = E>,
+{
+    let (p_lower, p_upper) = p.remaining_hint();
+    let (c_lower, c_upper) = c.capacity_hint();
+
+    if p_upper != Some(p_lower) && c_upper == Some(c_lower) {
+        bulk_produce_all(p, c)
+    } else {
+        bulk_consume_all(p, c)
+    }
+}
@@ -0,0 +1,274 @@
+//! A blocking multi-producer, single-consumer channel.
+//!
+//! The `Sender`s of a channel are `BulkConsumer`s, its `Receiver` is a `BulkProducer`. Instead of
+//! emitting an error when the channel is full or empty, they block the current thread until the
+//! other side has made progress. This allows e.g. several worker threads to `bulk_consume_all`
+//! into clones of the same `Sender`, while another thread aggregates everything via the
+//! `Receiver`.
+extern crate maybe_std as base;
+
+use base::num::NonZeroUsize;
+use base::mem::MaybeUninit;
+use base::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use base::thread;
+use core::cmp::min;
+
+use slice_n::Slice1;
+
+use crate::*;
+use crate::ringbuffer::FixedBuffer;
+
+/// Creates a new channel that can buffer up to `capacity` items, returning its first `Sender` and
+/// its `Receiver`. Further `Sender`s can be obtained by cloning.
+pub fn channel<T: Copy>(capacity: NonZeroUsize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buf: FixedBuffer::new(capacity),
+            senders: 1,
+            panicked: false,
+            receiver: true,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+        staging: FixedBuffer::new(capacity),
+    };
+    let receiver = Receiver {
+        shared,
+        staging: FixedBuffer::new(capacity),
+    };
+    (sender, receiver)
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    // signalled when items have been added or the last sender has been dropped
+    not_empty: Condvar,
+    // signalled when items have been removed or the receiver has been dropped
+    not_full: Condvar,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    buf: FixedBuffer<T>,
+    // number of live senders
+    senders: usize,
+    // whether any sender has been dropped while its thread was panicking
+    panicked: bool,
+    // whether the receiver is still alive
+    receiver: bool,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // The state is consistent whenever the lock is released, even when panicking.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Blocks until the channel has free space, returns `None` if the receiver has been dropped.
+    fn wait_for_space(&self) -> Option<MutexGuard<'_, State<T>>> {
+        let mut state = self.lock();
+        loop {
+            if !state.receiver {
+                return None;
+            } else if state.buf.get_amount() < state.buf.get_capacity().get() {
+                return Some(state);
+            }
+            state = self.not_full.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+// Moves as many items as possible from one buffer into the other.
+fn transfer<T: Copy>(from: &mut FixedBuffer<T>, to: &mut FixedBuffer<T>) {
+    loop {
+        let amount = match (from.producer_slots(), to.consumer_slots()) {
+            (Ok(src), Ok(dst)) => {
+                let amount = min(src.len_(), dst.len_());
+                write_slice(&mut dst[..amount], &src[..amount]);
+                unsafe { NonZeroUsize::new_unchecked(amount) }
+            }
+            _ => return,
+        };
+        unsafe { to.did_consume(amount) };
+        from.did_produce(amount);
+    }
+}
+
+/// The sending half of a channel. Blocks while the channel is full.
+///
+/// Items are handed to the channel as soon as they have been consumed, so flushing is not
+/// required for the `Receiver` to see them.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+    // holds the items written via `consumer_slots` until `did_consume` hands them to the channel
+    staging: FixedBuffer<T>,
+}
+
+impl<T: Copy> Consumer for Sender<T> {
+    type Item = T;
+    /// Emitted when the `Receiver` has been dropped.
+    type Error = ();
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        let mut state = self.shared.wait_for_space().ok_or(())?;
+        let _ = state.buf.consume(item);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.shared.lock().receiver {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl<T: Copy> BulkConsumer for Sender<T> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        if !self.shared.lock().receiver {
+            return Err(());
+        }
+
+        Ok(self.staging.consumer_slots().unwrap())
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        self.staging.did_consume(amount);
+
+        while self.staging.get_amount() > 0 {
+            match self.shared.wait_for_space() {
+                Some(mut state) => {
+                    transfer(&mut self.staging, &mut state.buf);
+                    self.shared.not_empty.notify_one();
+                }
+                // Nobody is going to receive the staged items, so they can be dropped.
+                None => self.staging.clear(),
+            }
+        }
+    }
+
+    fn bulk_consume(&mut self, data: &Slice1<Self::Item>) -> Result<NonZeroUsize, Self::Error> {
+        let mut state = self.shared.wait_for_space().ok_or(())?;
+        let amount = state.buf.bulk_consume(data).unwrap();
+        self.shared.not_empty.notify_one();
+        Ok(amount)
+    }
+}
+
+impl<T: Copy> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Sender {
+            shared: self.shared.clone(),
+            staging: FixedBuffer::new(self.staging.get_capacity()),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if thread::panicking() {
+            state.panicked = true;
+        }
+        if state.senders == 0 {
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+/// Emitted by a `Receiver` once all items have been received and all `Sender`s have been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Disconnected {
+    /// All `Sender`s have been dropped regularly.
+    Closed,
+    /// At least one `Sender` has been dropped while its thread was panicking, so the received
+    /// items might be incomplete.
+    Panicked,
+}
+
+/// The receiving half of a channel. Blocks while the channel is empty.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    // holds items that have been taken out of the channel but not yet produced
+    staging: FixedBuffer<T>,
+}
+
+impl<T: Copy> Receiver<T> {
+    // Blocks until the channel is nonempty or all senders are gone, then moves as many items as
+    // possible into the staging buffer.
+    fn fill(&mut self) -> Result<(), Disconnected> {
+        let mut state = self.shared.lock();
+        while state.buf.get_amount() == 0 {
+            if state.senders == 0 {
+                return Err(if state.panicked { Disconnected::Panicked } else { Disconnected::Closed });
+            }
+            state = self.shared.not_empty.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+
+        transfer(&mut state.buf, &mut self.staging);
+        self.shared.not_full.notify_all();
+        Ok(())
+    }
+}
+
+impl<T: Copy> Producer for Receiver<T> {
+    type Item = T;
+    type Error = Disconnected;
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        if self.staging.get_amount() == 0 {
+            self.fill()?;
+        }
+
+        Ok(self.staging.produce().unwrap())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.shared.lock();
+        transfer(&mut state.buf, &mut self.staging);
+        self.shared.not_full.notify_all();
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let state = self.shared.lock();
+        let available = self.staging.get_amount() + state.buf.get_amount();
+        if state.senders == 0 {
+            (available, Some(available))
+        } else {
+            (available, None)
+        }
+    }
+}
+
+impl<T: Copy> BulkProducer for Receiver<T> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        if self.staging.get_amount() == 0 {
+            self.fill()?;
+        }
+
+        Ok(self.staging.producer_slots().unwrap())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.staging.did_produce(amount)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver = false;
+        self.shared.not_full.notify_all();
+    }
+}
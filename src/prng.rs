@@ -0,0 +1,36 @@
+use core::num::NonZeroUsize;
+
+/// A tiny, seedable pseudo-random number generator (SplitMix64), used to derive operation
+/// schedules for the scramble adapters without a fuzzer. Not suitable for anything else.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..bound`. The slight bias is irrelevant for our purposes.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % (bound as u64)) as usize
+    }
+
+    /// Returns an amount of items for a bulk operation: mostly small amounts, with the occasional
+    /// large one.
+    pub fn amount(&mut self) -> NonZeroUsize {
+        let amount = if self.below(8) == 0 {
+            1 + self.below(4096)
+        } else {
+            1 + self.below(8)
+        };
+        NonZeroUsize::new(amount).unwrap()
+    }
+}
@@ -4,17 +4,46 @@ use core::cmp::min;
 
 use slice_n::Slice1;
 
+use crate::write_slice;
+
 mod cursor;
 pub use cursor::*;
 
+mod owned_cursor;
+pub use owned_cursor::*;
+
 mod map_err;
 pub use map_err::*;
 
-#[cfg(all(feature = "alloc", feature = "arbitrary"))]
+mod faulty;
+pub use faulty::*;
+
+mod timeout;
+pub use timeout::*;
+
+mod iter;
+pub use iter::{from_iter, FromIter, IntoItems, ByRefIter};
+
+#[cfg(feature = "alloc")]
+mod repeat;
+#[cfg(feature = "alloc")]
+pub use repeat::*;
+
+#[cfg(feature = "alloc")]
+mod collections;
+#[cfg(feature = "alloc")]
+pub use collections::*;
+
+#[cfg(feature = "alloc")]
 mod scramble;
-#[cfg(all(feature = "alloc", feature = "arbitrary"))]
+#[cfg(feature = "alloc")]
 pub use scramble::*;
 
+#[cfg(feature = "alloc")]
+mod record;
+#[cfg(feature = "alloc")]
+pub use record::*;
+
 /// A `Producer` produces items one by one.
 pub trait Producer {
     /// The type of values that are produced by the `Producer`.
@@ -30,6 +59,27 @@ pub trait Producer {
     /// is requested to be produced. This method instructs the `Producer` to move as much data from
     /// the data source into the internal buffer as possible.
     fn slurp(&mut self) -> Result<(), Self::Error>;
+
+    /// Returns bounds on the number of items the `Producer` can produce before it emits an error,
+    /// in the same way as `Iterator::size_hint`. The lower bound must not exceed the true number,
+    /// an upper bound of `None` means no upper bound is known. The default implementation returns
+    /// `(0, None)`, which is correct for any `Producer`.
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+
+    /// Turns the `Producer` into an `Iterator` that yields items until the `Producer` emits an
+    /// error. The error stays available on the iterator afterwards.
+    fn into_items(self) -> IntoItems<Self> where Self: Sized {
+        iter::into_items(self)
+    }
+
+    /// Returns an `Iterator` that yields items from the `Producer` until it emits an error,
+    /// without taking ownership of the `Producer`. The error stays available on the iterator
+    /// afterwards.
+    fn by_ref_iter(&mut self) -> ByRefIter<'_, Self> where Self: Sized {
+        iter::by_ref_iter(self)
+    }
 }
 
 /// A `BulkProducer` can produce multiple pieces of copyable data at a time.
@@ -40,16 +90,40 @@ pub trait BulkProducer: Producer where Self::Item: Copy {
     /// Tells the `BulkProducer` that some amount of items has been taken from it.
     fn did_produce(&mut self, amount: NonZeroUsize);
 
+    /// Returns two buffers from which items can be taken, the items of the second one following
+    /// those of the first. Only the first one is guaranteed to be nonempty. This allows e.g. ring
+    /// buffers to expose all of their data even when it wraps around. A subsequent `did_produce`
+    /// may report more items than are in the first buffer, but no more than in both buffers
+    /// combined.
+    ///
+    /// The default implementation returns `producer_slots` and an empty second buffer.
+    fn producer_slots_vectored(&mut self) -> Result<(&Slice1<Self::Item>, &[Self::Item]), Self::Error> {
+        Ok((self.producer_slots()?, &[]))
+    }
+
     /// The `BulkProducer` produces a non-zero number of items into the provided buffer, and
     /// returns how many it has produced. The memory in the buffer does not need to be initialized.
     fn bulk_produce(&mut self, buffer: &mut Slice1<MaybeUninit<Self::Item>>) -> Result<NonZeroUsize, Self::Error> {
         let r = self.producer_slots()?;
         let amount = min(r.len_(), buffer.len_());
-        MaybeUninit::write_slice(&mut buffer[..amount], &r[..amount]);
+        write_slice(&mut buffer[..amount], &r[..amount]);
         unsafe {
             let amount = NonZeroUsize::new_unchecked(amount);
             self.did_produce(amount);
             Ok(amount)
         }
     }
+
+    /// The `BulkProducer` produces a non-zero number of items into the provided buffers, filling
+    /// the second one only after the first one is full, and returns how many it has produced.
+    /// The memory in the buffers does not need to be initialized.
+    ///
+    /// The default implementation only produces into the first buffer.
+    fn bulk_produce_vectored(
+        &mut self,
+        fst: &mut Slice1<MaybeUninit<Self::Item>>,
+        _snd: &mut [MaybeUninit<Self::Item>],
+    ) -> Result<NonZeroUsize, Self::Error> {
+        self.bulk_produce(fst)
+    }
 }
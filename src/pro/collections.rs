@@ -0,0 +1,163 @@
+extern crate maybe_std as base;
+
+use base::collections::VecDeque;
+use base::vec::Vec;
+use core::mem;
+use core::num::NonZeroUsize;
+use core::ptr;
+use core::slice;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::pro::*;
+
+/// Creates a producer which moves the items out of the front of the given `Vec`.
+pub fn drain_vec<'a, T>(v: &'a mut Vec<T>) -> DrainVec<'a, T> {
+    let len = v.len();
+    // Items before `read` are moved out, so the `Vec` must not see them while we are active. If
+    // the `DrainVec` is leaked, the remaining items are leaked as well rather than double-dropped.
+    unsafe { v.set_len(0) };
+    DrainVec { vec: v, read: 0, len }
+}
+
+/// Produces items by moving them out of the front of a `Vec`. Once the `DrainVec` is dropped or
+/// unwrapped, the `Vec` contains exactly the items that have not been produced.
+pub struct DrainVec<'a, T> {
+    vec: &'a mut Vec<T>,
+    // producing resumes from this position
+    read: usize,
+    // length of the `Vec` before draining started
+    len: usize,
+}
+
+impl<'a, T> DrainVec<'a, T> {
+    fn remaining(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.vec.as_ptr().add(self.read), self.len - self.read) }
+    }
+
+    fn restore(&mut self) {
+        let remaining = self.len - self.read;
+        unsafe {
+            ptr::copy(self.vec.as_ptr().add(self.read), self.vec.as_mut_ptr(), remaining);
+            self.vec.set_len(remaining);
+        }
+        self.read = 0;
+        self.len = remaining;
+    }
+}
+
+impl<'a, T> Drop for DrainVec<'a, T> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+impl<'a, T> Wrapper<&'a mut Vec<T>> for DrainVec<'a, T> {
+    fn into_inner(mut self) -> &'a mut Vec<T> {
+        self.restore();
+        let vec = unsafe { ptr::read(&self.vec) };
+        mem::forget(self);
+        vec
+    }
+}
+
+impl<'a, T> AsRef<[T]> for DrainVec<'a, T> {
+    fn as_ref(&self) -> &[T] {
+        self.remaining()
+    }
+}
+
+impl<'a, T> Producer for DrainVec<'a, T> {
+    type Item = T;
+    /// Emitted when the `Vec` has been drained completely.
+    type Error = ();
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        if self.read == self.len {
+            Err(())
+        } else {
+            let item = unsafe { ptr::read(self.vec.as_ptr().add(self.read)) };
+            self.read += 1;
+            Ok(item)
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.read;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: Copy> BulkProducer for DrainVec<'a, T> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Slice1::from_slice(self.remaining()).ok_or(())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.read += amount.get();
+    }
+}
+
+/// Creates a producer which pops the items from the front of the given `VecDeque`.
+pub fn drain_vec_deque<'a, T>(v: &'a mut VecDeque<T>) -> DrainVecDeque<'a, T> {
+    DrainVecDeque(v)
+}
+
+/// Produces items by popping them from the front of a `VecDeque`.
+pub struct DrainVecDeque<'a, T>(&'a mut VecDeque<T>);
+
+impl<'a, T> Wrapper<&'a mut VecDeque<T>> for DrainVecDeque<'a, T> {
+    fn into_inner(self) -> &'a mut VecDeque<T> {
+        self.0
+    }
+}
+
+impl<'a, T> AsRef<VecDeque<T>> for DrainVecDeque<'a, T> {
+    fn as_ref(&self) -> &VecDeque<T> {
+        self.0
+    }
+}
+
+impl<'a, T> AsMut<VecDeque<T>> for DrainVecDeque<'a, T> {
+    fn as_mut(&mut self) -> &mut VecDeque<T> {
+        self.0
+    }
+}
+
+impl<'a, T> Producer for DrainVecDeque<'a, T> {
+    type Item = T;
+    /// Emitted when the `VecDeque` is empty.
+    type Error = ();
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        self.0.pop_front().ok_or(())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+
+impl<'a, T: Copy> BulkProducer for DrainVecDeque<'a, T> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Slice1::from_slice(self.0.as_slices().0).ok_or(())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.0.drain(..amount.get());
+    }
+
+    fn producer_slots_vectored(&mut self) -> Result<(&Slice1<Self::Item>, &[Self::Item]), Self::Error> {
+        let (fst, snd) = self.0.as_slices();
+        Ok((Slice1::from_slice(fst).ok_or(())?, snd))
+    }
+}
@@ -1,10 +1,13 @@
+use core::cmp::min;
 use core::convert::AsRef;
+use core::mem::MaybeUninit;
 use core::num::NonZeroUsize;
 
 use slice_n::Slice1;
 use wrapper::Wrapper;
 
 use crate::pro::*;
+use crate::write_slice;
 
 /// Creates a producer which produces the data in the given slice.
 pub fn cursor<'a, T>(s: &'a [T]) -> Cursor<'a, T> {
@@ -44,6 +47,11 @@ impl<'a, T: Clone> Producer for Cursor<'a, T> {
     fn slurp(&mut self) -> Result<(), Self::Error> {
         Ok(())
     }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.0.len() - self.1;
+        (remaining, Some(remaining))
+    }
 }
 
 impl<'a, T: Copy> BulkProducer for Cursor<'a, T> {
@@ -54,4 +62,21 @@ impl<'a, T: Copy> BulkProducer for Cursor<'a, T> {
     fn did_produce(&mut self, amount: NonZeroUsize) {
         self.1 += amount.get();
     }
+
+    fn bulk_produce_vectored(
+        &mut self,
+        fst: &mut Slice1<MaybeUninit<Self::Item>>,
+        snd: &mut [MaybeUninit<Self::Item>],
+    ) -> Result<NonZeroUsize, Self::Error> {
+        let r = self.producer_slots()?;
+        let fst_amount = min(r.len_(), fst.len_());
+        write_slice(&mut fst[..fst_amount], &r[..fst_amount]);
+        let snd_amount = min(r.len_() - fst_amount, snd.len());
+        write_slice(&mut snd[..snd_amount], &r[fst_amount..fst_amount + snd_amount]);
+        unsafe {
+            let amount = NonZeroUsize::new_unchecked(fst_amount + snd_amount);
+            self.did_produce(amount);
+            Ok(amount)
+        }
+    }
 }
@@ -0,0 +1,189 @@
+use core::cmp::min;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::pro::*;
+
+/// The failures a `FaultyProducer` injects. The `Default` injects none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProduceFaults {
+    /// Emit an error instead of producing any items once this many have been produced.
+    pub error_after: Option<usize>,
+    /// Emit an error on the given call to `slurp`, counting from one.
+    pub error_on_slurp: Option<NonZeroUsize>,
+    /// Shorten the buffers returned by `producer_slots` to a single item.
+    pub single_item_slots: bool,
+    /// Produce at most this many items per call to `bulk_produce`.
+    pub max_bulk_produce: Option<NonZeroUsize>,
+}
+
+/// Wraps a producer and injects failures into it, according to some `ProduceFaults`.
+///
+/// Panics if any method is called after an error has been emitted, be it an injected one or one
+/// of the wrapped producer.
+#[derive(Debug)]
+pub struct FaultyProducer<I, E> {
+    inner: I,
+    faults: ProduceFaults,
+    // the injected error
+    error: E,
+    produced: usize,
+    slurped: usize,
+    failed: bool,
+}
+
+impl<I, E> FaultyProducer<I, E> {
+    /// Creates a wrapper that injects the given faults, emitting clones of `error` for them.
+    pub fn new(inner: I, faults: ProduceFaults, error: E) -> Self {
+        FaultyProducer {
+            inner,
+            faults,
+            error,
+            produced: 0,
+            slurped: 0,
+            failed: false,
+        }
+    }
+
+    /// Returns whether an error has been emitted.
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    fn check_not_failed(&self, method: &str) {
+        if self.failed {
+            panic!("FaultyProducer: `{}` was called after an error had been emitted", method);
+        }
+    }
+
+    // How many more items may be produced before the injected error.
+    fn allowance(&self) -> usize {
+        match self.faults.error_after {
+            Some(n) => n.saturating_sub(self.produced),
+            None => usize::MAX,
+        }
+    }
+}
+
+impl<I, E: Clone> FaultyProducer<I, E> {
+    fn fail<T>(&mut self, result: Result<T, E>) -> Result<T, E> {
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    fn inject<T>(&mut self) -> Result<T, E> {
+        self.failed = true;
+        Err(self.error.clone())
+    }
+}
+
+impl<I, E> Wrapper<I> for FaultyProducer<I, E> {
+    fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I, E> AsRef<I> for FaultyProducer<I, E> {
+    fn as_ref(&self) -> &I {
+        &self.inner
+    }
+}
+
+impl<I, E> AsMut<I> for FaultyProducer<I, E> {
+    fn as_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+}
+
+impl<I: Producer<Error = E>, E: Clone> Producer for FaultyProducer<I, E> {
+    type Item = I::Item;
+    type Error = E;
+
+    fn produce(&mut self) -> Result<Self::Item, Self::Error> {
+        self.check_not_failed("produce");
+        if self.allowance() == 0 {
+            return self.inject();
+        }
+
+        let result = self.inner.produce();
+        if result.is_ok() {
+            self.produced += 1;
+        }
+        self.fail(result)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.check_not_failed("slurp");
+        self.slurped += 1;
+        if self.faults.error_on_slurp.map_or(false, |k| k.get() == self.slurped) {
+            return self.inject();
+        }
+
+        let result = self.inner.slurp();
+        self.fail(result)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.inner.remaining_hint();
+        if self.faults.error_after.is_none() {
+            return (lower, upper);
+        }
+
+        let allowance = self.allowance();
+        (min(lower, allowance), Some(upper.map_or(allowance, |upper| min(upper, allowance))))
+    }
+}
+
+impl<I: BulkProducer<Error = E>, E: Clone> BulkProducer for FaultyProducer<I, E> where I::Item: Copy {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        self.check_not_failed("producer_slots");
+        let mut allowance = self.allowance();
+        if allowance == 0 {
+            return self.inject();
+        }
+        if self.faults.single_item_slots {
+            allowance = 1;
+        }
+
+        match self.inner.producer_slots() {
+            Ok(slots) => {
+                let len = min(slots.len_(), allowance);
+                Ok(unsafe { Slice1::from_slice_unchecked(&slots[..len]) })
+            }
+            Err(e) => {
+                self.failed = true;
+                Err(e)
+            }
+        }
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.check_not_failed("did_produce");
+        self.produced += amount.get();
+        self.inner.did_produce(amount)
+    }
+
+    fn bulk_produce(&mut self, buffer: &mut Slice1<MaybeUninit<Self::Item>>) -> Result<NonZeroUsize, Self::Error> {
+        self.check_not_failed("bulk_produce");
+        let mut allowance = self.allowance();
+        if allowance == 0 {
+            return self.inject();
+        }
+        if let Some(max) = self.faults.max_bulk_produce {
+            allowance = min(allowance, max.get());
+        }
+
+        let len = min(buffer.len_(), allowance);
+        let buffer = unsafe { Slice1::from_slice_unchecked_mut(&mut buffer[..len]) };
+        let result = self.inner.bulk_produce(buffer);
+        if let Ok(amount) = result {
+            self.produced += amount.get();
+        }
+        self.fail(result)
+    }
+}
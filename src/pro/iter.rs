@@ -0,0 +1,201 @@
+extern crate maybe_std as base;
+
+use core::array;
+use core::iter::Iterator;
+use core::num::NonZeroUsize;
+
+#[cfg(feature = "alloc")]
+use base::vec;
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::pro::*;
+
+/// Creates a producer which produces the items of the given iterator.
+pub fn from_iter<I: IntoIterator>(iter: I) -> FromIter<I::IntoIter> {
+    FromIter(iter.into_iter())
+}
+
+/// Produces the items of an `Iterator`.
+///
+/// Iterators over owned arrays and vectors can also be used as a `BulkProducer`, going through
+/// their `as_slice` method.
+pub struct FromIter<I>(I);
+
+impl<I> Wrapper<I> for FromIter<I> {
+    fn into_inner(self) -> I {
+        self.0
+    }
+}
+
+impl<I> AsRef<I> for FromIter<I> {
+    fn as_ref(&self) -> &I {
+        &self.0
+    }
+}
+
+impl<I> AsMut<I> for FromIter<I> {
+    fn as_mut(&mut self) -> &mut I {
+        &mut self.0
+    }
+}
+
+impl<I: Iterator> Producer for FromIter<I> {
+    type Item = I::Item;
+    /// Emitted when the iterator has been exhausted.
+    type Error = ();
+
+    fn produce(&mut self) -> Result<Self::Item, Self::Error> {
+        self.0.next().ok_or(())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T: Copy, const N: usize> BulkProducer for FromIter<array::IntoIter<T, N>> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Slice1::from_slice(self.0.as_slice()).ok_or(())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.0.nth(amount.get() - 1);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Copy> BulkProducer for FromIter<vec::IntoIter<T>> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Slice1::from_slice(self.0.as_slice()).ok_or(())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.0.nth(amount.get() - 1);
+    }
+}
+
+/// An `Iterator` yielding the items of a `Producer` until it emits an error. Created by
+/// `Producer::into_items`.
+pub struct IntoItems<P: Producer> {
+    inner: P,
+    error: Option<P::Error>,
+    done: bool,
+}
+
+impl<P: Producer> IntoItems<P> {
+    /// Returns the error that ended the iteration, if any.
+    pub fn error(&self) -> Option<&P::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error that ended the iteration, if any. The iteration stays over, the iterator
+    /// does not pull any further items from the `Producer`.
+    pub fn take_error(&mut self) -> Option<P::Error> {
+        self.error.take()
+    }
+
+    /// Returns the `Producer` together with the error that ended the iteration, if any.
+    pub fn into_parts(self) -> (P, Option<P::Error>) {
+        (self.inner, self.error)
+    }
+}
+
+impl<P: Producer> Wrapper<P> for IntoItems<P> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Producer> AsRef<P> for IntoItems<P> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P: Producer> Iterator for IntoItems<P> {
+    type Item = P::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next(&mut self.inner, &mut self.error, &mut self.done)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(&self.inner, self.done)
+    }
+}
+
+/// An `Iterator` yielding the items of a borrowed `Producer` until it emits an error. Created by
+/// `Producer::by_ref_iter`.
+pub struct ByRefIter<'a, P: Producer> {
+    inner: &'a mut P,
+    error: Option<P::Error>,
+    done: bool,
+}
+
+impl<'a, P: Producer> ByRefIter<'a, P> {
+    /// Returns the error that ended the iteration, if any.
+    pub fn error(&self) -> Option<&P::Error> {
+        self.error.as_ref()
+    }
+
+    /// Takes the error that ended the iteration, if any. The iteration stays over, the iterator
+    /// does not pull any further items from the `Producer`.
+    pub fn take_error(&mut self) -> Option<P::Error> {
+        self.error.take()
+    }
+
+    /// Consumes the iterator and returns the error that ended the iteration, if any.
+    pub fn into_error(self) -> Option<P::Error> {
+        self.error
+    }
+}
+
+impl<'a, P: Producer> Iterator for ByRefIter<'a, P> {
+    type Item = P::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next(self.inner, &mut self.error, &mut self.done)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.inner, self.done)
+    }
+}
+
+pub(crate) fn into_items<P: Producer>(inner: P) -> IntoItems<P> {
+    IntoItems { inner, error: None, done: false }
+}
+
+pub(crate) fn by_ref_iter<'a, P: Producer>(inner: &'a mut P) -> ByRefIter<'a, P> {
+    ByRefIter { inner, error: None, done: false }
+}
+
+// The behavior of a producer after it has emitted an error is unspecified, so the iteration ends
+// with the first error, even if that error has been taken in the meantime.
+fn next<P: Producer>(p: &mut P, error: &mut Option<P::Error>, done: &mut bool) -> Option<P::Item> {
+    if *done {
+        return None;
+    }
+
+    match p.produce() {
+        Ok(item) => Some(item),
+        Err(e) => {
+            *error = Some(e);
+            *done = true;
+            None
+        }
+    }
+}
+
+fn size_hint<P: Producer>(p: &P, done: bool) -> (usize, Option<usize>) {
+    if done {
+        (0, Some(0))
+    } else {
+        p.remaining_hint()
+    }
+}
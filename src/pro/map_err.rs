@@ -1,5 +1,6 @@
 use core::convert::{AsRef, AsMut};
 use core::num::NonZeroUsize;
+use core::mem::MaybeUninit;
 
 use slice_n::Slice1;
 use wrapper::Wrapper;
@@ -50,6 +51,10 @@ impl<I, F, T, E, E2> Producer for MapErr<I, F> where
             Err(e) => Err(self.1(e))
         }
     }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        self.0.remaining_hint()
+    }
 }
 
 impl<I, F, T, E, E2> BulkProducer for MapErr<I, F> where
@@ -67,4 +72,29 @@ impl<I, F, T, E, E2> BulkProducer for MapErr<I, F> where
     fn did_produce(&mut self, amount: NonZeroUsize) {
         self.0.did_produce(amount)
     }
+
+    fn producer_slots_vectored(&mut self) -> Result<(&Slice1<Self::Item>, &[Self::Item]), Self::Error> {
+        match self.0.producer_slots_vectored() {
+            Ok(s) => Ok(s),
+            Err(e) => Err(self.1(e))
+        }
+    }
+
+    fn bulk_produce(&mut self, buffer: &mut Slice1<MaybeUninit<Self::Item>>) -> Result<NonZeroUsize, Self::Error> {
+        match self.0.bulk_produce(buffer) {
+            Ok(amount) => Ok(amount),
+            Err(e) => Err(self.1(e))
+        }
+    }
+
+    fn bulk_produce_vectored(
+        &mut self,
+        fst: &mut Slice1<MaybeUninit<Self::Item>>,
+        snd: &mut [MaybeUninit<Self::Item>],
+    ) -> Result<NonZeroUsize, Self::Error> {
+        match self.0.bulk_produce_vectored(fst, snd) {
+            Ok(amount) => Ok(amount),
+            Err(e) => Err(self.1(e))
+        }
+    }
 }
@@ -0,0 +1,152 @@
+use core::convert::AsRef;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::num::NonZeroUsize;
+use core::ptr;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::pro::*;
+use crate::slice_assume_init_ref;
+
+/// Creates a producer which produces the data in the given storage, e.g. a `Box<[T]>`, a
+/// `Vec<T>`, an `Arc<[T]>` or an array.
+pub fn owned_cursor<S: AsRef<[T]>, T>(s: S) -> OwnedCursor<S, T> {
+    OwnedCursor(s, 0, PhantomData)
+}
+
+/// Produces data from some storage it owns. Unwrapping yields the storage and the number of items
+/// that have been produced.
+pub struct OwnedCursor<S, T>(S, usize, PhantomData<T>);
+
+impl<S, T> OwnedCursor<S, T> {
+    /// Returns the number of items that have been produced so far.
+    pub fn position(&self) -> usize {
+        self.1
+    }
+}
+
+impl<S, T> Wrapper<(S, usize)> for OwnedCursor<S, T> {
+    fn into_inner(self) -> (S, usize) {
+        (self.0, self.1)
+    }
+}
+
+impl<S: AsRef<[T]>, T> AsRef<[T]> for OwnedCursor<S, T> {
+    fn as_ref(&self) -> &[T] {
+        self.0.as_ref()
+    }
+}
+
+impl<S: AsRef<[T]>, T: Clone> Producer for OwnedCursor<S, T> {
+    type Item = T;
+    /// Emitted when the end of the storage has been reached.
+    type Error = ();
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        let s = self.0.as_ref();
+        if s.len() == self.1 {
+            Err(())
+        } else {
+            let item = s[self.1].clone();
+            self.1 += 1;
+            Ok(item)
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.0.as_ref().len() - self.1;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<S: AsRef<[T]>, T: Copy> BulkProducer for OwnedCursor<S, T> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Slice1::from_slice(&self.0.as_ref()[self.1..]).ok_or(())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.1 += amount.get();
+    }
+}
+
+/// Creates a producer which moves the items out of the given array, so they need not be `Clone`.
+pub fn array_cursor<T, const N: usize>(a: [T; N]) -> ArrayCursor<T, N> {
+    ArrayCursor(unsafe { mem::transmute_copy(&mem::ManuallyDrop::new(a)) }, 0)
+}
+
+/// Produces the items of an array by value. Unwrapping yields the array, in which exactly the
+/// items from the returned position onwards are initialized, together with that position.
+pub struct ArrayCursor<T, const N: usize>([MaybeUninit<T>; N], usize);
+
+impl<T, const N: usize> ArrayCursor<T, N> {
+    /// Returns the number of items that have been produced so far.
+    pub fn position(&self) -> usize {
+        self.1
+    }
+
+    fn remaining(&self) -> &[T] {
+        unsafe { slice_assume_init_ref(&self.0[self.1..]) }
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayCursor<T, N> {
+    fn drop(&mut self) {
+        for item in self.0[self.1..].iter_mut() {
+            unsafe { item.assume_init_drop() };
+        }
+    }
+}
+
+impl<T, const N: usize> Wrapper<([MaybeUninit<T>; N], usize)> for ArrayCursor<T, N> {
+    fn into_inner(self) -> ([MaybeUninit<T>; N], usize) {
+        let this = mem::ManuallyDrop::new(self);
+        (unsafe { ptr::read(&this.0) }, this.1)
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for ArrayCursor<T, N> {
+    /// Returns the items that have not been produced yet.
+    fn as_ref(&self) -> &[T] {
+        self.remaining()
+    }
+}
+
+impl<T, const N: usize> Producer for ArrayCursor<T, N> {
+    type Item = T;
+    /// Emitted when the end of the array has been reached.
+    type Error = ();
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        if self.1 == N {
+            Err(())
+        } else {
+            let item = unsafe { self.0[self.1].assume_init_read() };
+            self.1 += 1;
+            Ok(item)
+        }
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (N - self.1, Some(N - self.1))
+    }
+}
+
+impl<T: Copy, const N: usize> BulkProducer for ArrayCursor<T, N> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Slice1::from_slice(self.remaining()).ok_or(())
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.1 += amount.get();
+    }
+}
@@ -0,0 +1,271 @@
+extern crate maybe_std as base;
+
+use base::vec::Vec;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::pro::*;
+
+/// The methods of a `BulkProducer` that can emit an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProduceMethod {
+    Produce,
+    ProducerSlots,
+    BulkProduce,
+    Slurp,
+}
+
+/// A single call to a method of a `BulkProducer`, as logged by a `Recorder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProduceEvent {
+    /// `produce` returned an item.
+    Produce,
+    /// `producer_slots` returned a buffer of the given length, or `producer_slots_vectored`
+    /// returned buffers of the given combined length.
+    ProducerSlots(NonZeroUsize),
+    /// `did_produce` was called with the given amount.
+    DidProduce(NonZeroUsize),
+    /// `bulk_produce` was given a buffer of the first length, and produced the second amount.
+    BulkProduce(NonZeroUsize, NonZeroUsize),
+    /// `slurp` returned successfully.
+    Slurp,
+    /// The given method emitted an error.
+    Error(ProduceMethod),
+}
+
+/// Wraps a producer and logs every method call into a list of `ProduceEvent`s.
+#[derive(Debug)]
+pub struct Recorder<P> {
+    inner: P,
+    events: Vec<ProduceEvent>,
+}
+
+impl<P> Recorder<P> {
+    pub fn new(inner: P) -> Self {
+        Recorder {
+            inner,
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the events that have been recorded so far.
+    pub fn events(&self) -> &[ProduceEvent] {
+        &self.events
+    }
+
+    /// Returns the wrapped producer and the recorded events.
+    pub fn into_parts(self) -> (P, Vec<ProduceEvent>) {
+        (self.inner, self.events)
+    }
+
+    fn record<T, E>(&mut self, result: Result<T, E>, method: ProduceMethod, event: ProduceEvent) -> Result<T, E> {
+        match result {
+            Ok(_) => self.events.push(event),
+            Err(_) => self.events.push(ProduceEvent::Error(method)),
+        }
+        result
+    }
+}
+
+impl<P> Wrapper<P> for Recorder<P> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P> AsRef<P> for Recorder<P> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P> AsMut<P> for Recorder<P> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: Producer> Producer for Recorder<P> {
+    type Item = P::Item;
+    type Error = P::Error;
+
+    fn produce(&mut self) -> Result<Self::Item, Self::Error> {
+        let result = self.inner.produce();
+        self.record(result, ProduceMethod::Produce, ProduceEvent::Produce)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        let result = self.inner.slurp();
+        self.record(result, ProduceMethod::Slurp, ProduceEvent::Slurp)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        self.inner.remaining_hint()
+    }
+}
+
+impl<P: BulkProducer> BulkProducer for Recorder<P> where P::Item: Copy {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        match self.inner.producer_slots() {
+            Ok(slots) => {
+                self.events.push(ProduceEvent::ProducerSlots(slots.len()));
+                Ok(slots)
+            }
+            Err(e) => {
+                self.events.push(ProduceEvent::Error(ProduceMethod::ProducerSlots));
+                Err(e)
+            }
+        }
+    }
+
+    fn producer_slots_vectored(&mut self) -> Result<(&Slice1<Self::Item>, &[Self::Item]), Self::Error> {
+        match self.inner.producer_slots_vectored() {
+            Ok((fst, snd)) => {
+                let len = unsafe { NonZeroUsize::new_unchecked(fst.len_() + snd.len()) };
+                self.events.push(ProduceEvent::ProducerSlots(len));
+                Ok((fst, snd))
+            }
+            Err(e) => {
+                self.events.push(ProduceEvent::Error(ProduceMethod::ProducerSlots));
+                Err(e)
+            }
+        }
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.events.push(ProduceEvent::DidProduce(amount));
+        self.inner.did_produce(amount)
+    }
+
+    fn bulk_produce(&mut self, buffer: &mut Slice1<MaybeUninit<Self::Item>>) -> Result<NonZeroUsize, Self::Error> {
+        let len = buffer.len();
+        match self.inner.bulk_produce(buffer) {
+            Ok(amount) => {
+                self.events.push(ProduceEvent::BulkProduce(len, amount));
+                Ok(amount)
+            }
+            Err(e) => {
+                self.events.push(ProduceEvent::Error(ProduceMethod::BulkProduce));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Emitted by a `Replayer` when a producer does not behave as recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the first event that could not be reproduced.
+    pub index: usize,
+}
+
+/// Performs a recorded sequence of `ProduceEvent`s on a fresh producer, checking that it behaves
+/// exactly like the recorded one.
+#[derive(Debug, Clone, Copy)]
+pub struct Replayer<'a>(&'a [ProduceEvent]);
+
+impl<'a> Replayer<'a> {
+    pub fn new(events: &'a [ProduceEvent]) -> Self {
+        Replayer(events)
+    }
+
+    /// Performs the recorded method calls on the given producer, stopping at the first one with
+    /// a different outcome than recorded. Slots are requested via `producer_slots_vectored`, and
+    /// match a recorded length if either the first buffer or both buffers together have it.
+    pub fn replay<P: BulkProducer>(&self, p: &mut P) -> Result<(), Divergence> where P::Item: Copy {
+        let mut buffer = Vec::new();
+
+        for (index, event) in self.0.iter().enumerate() {
+            let divergence = Divergence { index };
+            match *event {
+                ProduceEvent::Produce => {
+                    p.produce().map_err(|_| divergence)?;
+                }
+                ProduceEvent::ProducerSlots(len) => {
+                    // The recorded call might have been vectored or not.
+                    let (fst, snd) = p.producer_slots_vectored().map_err(|_| divergence)?;
+                    if fst.len() != len && fst.len_() + snd.len() != len.get() {
+                        return Err(divergence);
+                    }
+                }
+                ProduceEvent::DidProduce(amount) => p.did_produce(amount),
+                ProduceEvent::BulkProduce(len, amount) => {
+                    buffer.clear();
+                    buffer.resize_with(len.get(), MaybeUninit::uninit);
+                    let slots = unsafe { Slice1::from_slice_unchecked_mut(&mut buffer[..]) };
+                    if p.bulk_produce(slots).map_err(|_| divergence)? != amount {
+                        return Err(divergence);
+                    }
+                }
+                ProduceEvent::Slurp => p.slurp().map_err(|_| divergence)?,
+                ProduceEvent::Error(method) => {
+                    let failed = match method {
+                        ProduceMethod::Produce => p.produce().is_err(),
+                        ProduceMethod::ProducerSlots => p.producer_slots().is_err(),
+                        ProduceMethod::BulkProduce => {
+                            buffer.clear();
+                            buffer.push(MaybeUninit::uninit());
+                            let slots = unsafe { Slice1::from_slice_unchecked_mut(&mut buffer[..]) };
+                            p.bulk_produce(slots).is_err()
+                        }
+                        ProduceMethod::Slurp => p.slurp().is_err(),
+                    };
+                    if !failed {
+                        return Err(divergence);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ProduceOperations {
+    /// Converts recorded events into the corresponding scramble operations, so that a recorded
+    /// interaction can be rerun under a `ScrambleProducer`. Returns `None` if there are no
+    /// operations other than `slurp`.
+    pub fn from_events(events: &[ProduceEvent]) -> Option<Self> {
+        let mut operations = Vec::new();
+        let mut events = events.iter().peekable();
+
+        while let Some(event) = events.next() {
+            match *event {
+                ProduceEvent::Produce | ProduceEvent::Error(ProduceMethod::Produce) => {
+                    operations.push(ProduceOperation::Produce);
+                }
+                ProduceEvent::ProducerSlots(len) => {
+                    let amount = match events.peek() {
+                        Some(ProduceEvent::DidProduce(amount)) => {
+                            let amount = *amount;
+                            events.next();
+                            amount
+                        }
+                        _ => len,
+                    };
+                    operations.push(ProduceOperation::ProducerSlots(amount));
+                }
+                ProduceEvent::Error(ProduceMethod::ProducerSlots) => {
+                    operations.push(ProduceOperation::ProducerSlots(NonZeroUsize::new(1).unwrap()));
+                }
+                ProduceEvent::DidProduce(amount) => {
+                    operations.push(ProduceOperation::ProducerSlots(amount));
+                }
+                ProduceEvent::BulkProduce(len, _) => {
+                    operations.push(ProduceOperation::BulkProduce(len));
+                }
+                ProduceEvent::Error(ProduceMethod::BulkProduce) => {
+                    operations.push(ProduceOperation::BulkProduce(NonZeroUsize::new(1).unwrap()));
+                }
+                ProduceEvent::Slurp | ProduceEvent::Error(ProduceMethod::Slurp) => {
+                    operations.push(ProduceOperation::Slurp);
+                }
+            }
+        }
+
+        ProduceOperations::new(operations.into_boxed_slice())
+    }
+}
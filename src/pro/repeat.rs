@@ -0,0 +1,80 @@
+extern crate maybe_std as base;
+
+use base::num::NonZeroUsize;
+use base::boxed::Box;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::allocator::*;
+use crate::pro::{Producer, BulkProducer};
+use crate::Infallible;
+
+/// A producer that repeatedly outputs the same data.
+pub struct Repeat<T, A: Allocator = Global>(BoxIn<Slice1<T>, A>, usize);
+
+impl<T> Repeat<T> {
+    /// Create a new `Repeat`, endlessly repeating the given data.
+    pub fn new(data: Box<Slice1<T>>) -> Self {
+        Repeat(in_global(data), 0)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<T, A: Allocator> Repeat<T, A> {
+    /// Create a new `Repeat`, endlessly repeating the given data, which has been allocated by an
+    /// arbitrary allocator.
+    pub fn new_in(data: Box<Slice1<T>, A>) -> Self {
+        Repeat(data, 0)
+    }
+}
+
+impl<T, A: Allocator> Repeat<T, A> {
+    /// The index of the item that will be produced next.
+    pub fn position(&self) -> usize {
+        self.1
+    }
+}
+
+impl<T: Clone, A: Allocator> Producer for Repeat<T, A> {
+    type Item = T;
+    type Error = Infallible;
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        let old_index = self.1;
+        self.1 = (self.1 + 1) % self.0.len_();
+        Ok(self.0[old_index].clone())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T: Copy, A: Allocator> BulkProducer for Repeat<T, A> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        Ok(unsafe { Slice1::from_slice_unchecked(&self.0[self.1..]) })
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.1 = (self.1 + amount.get()) % self.0.len_();
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<T, A: Allocator> Wrapper<Box<Slice1<T>, A>> for Repeat<T, A> {
+    fn into_inner(self) -> Box<Slice1<T>, A> {
+        self.0
+    }
+}
+
+#[cfg(not(feature = "unstable"))]
+impl<T> Wrapper<Box<Slice1<T>>> for Repeat<T> {
+    fn into_inner(self) -> Box<Slice1<T>> {
+        out_of_global(self.0)
+    }
+}
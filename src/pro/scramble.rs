@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 use maybe_std::{
     boxed::Box,
+    vec::Vec,
     cmp::min,
     num::NonZeroUsize,
 };
@@ -8,12 +9,16 @@ use maybe_std::{
 use slice_n::Slice1;
 use wrapper::Wrapper;
 
+#[cfg(feature = "arbitrary")]
 use arbitrary::{Arbitrary, Error, Unstructured};
 
 use crate::*;
+use crate::prng::SplitMix64;
+use crate::allocator::*;
 use ringbuffer::*;
 
-#[derive(Debug, PartialEq, Eq, Arbitrary)]
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 pub enum ProduceOperation {
     Produce,
     ProducerSlots(NonZeroUsize),
@@ -43,6 +48,31 @@ impl ProduceOperations {
     }
 }
 
+impl ProduceOperations {
+    /// Deterministically derives between one and sixteen operations from the given seed.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let len = 1 + rng.below(16);
+        let mut operations = Vec::with_capacity(len);
+        for _ in 0..len {
+            let op = match rng.below(4) {
+                0 => ProduceOperation::Produce,
+                1 => ProduceOperation::ProducerSlots(rng.amount()),
+                2 => ProduceOperation::BulkProduce(rng.amount()),
+                _ => ProduceOperation::Slurp,
+            };
+            operations.push(op);
+        }
+
+        if operations.iter().all(|op| *op == ProduceOperation::Slurp) {
+            operations[0] = ProduceOperation::Produce;
+        }
+
+        ProduceOperations(operations.into_boxed_slice())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
 impl<'a> Arbitrary<'a> for ProduceOperations {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self, Error> {
         match Self::new(Arbitrary::arbitrary(u)?) {
@@ -58,9 +88,9 @@ impl<'a> Arbitrary<'a> for ProduceOperations {
 }
 
 #[derive(Debug)]
-pub struct ScrambleProducer<I, T, E> {
+pub struct ScrambleProducer<I, T, E, A: Allocator = Global> {
     inner: I,
-    buf: FixedBuffer<T>,
+    buf: FixedBuffer<T, A>,
     err: Option<E>,
     operations: Box<[ProduceOperation]>,
     operations_index: usize,
@@ -76,9 +106,41 @@ impl<I, T, E> ScrambleProducer<I, T, E> {
             operations_index: 0,
         }
     }
+
+    /// Creates a scrambling wrapper whose operations and buffer capacity are derived
+    /// deterministically from the given seed. Running a test over many seeds and reporting the
+    /// failing one allows for reproducible scrambling in ordinary unit tests.
+    pub fn from_seed(inner: I, seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let capacity = NonZeroUsize::new(1 + rng.below(64)).unwrap();
+        Self::new(inner, ProduceOperations::from_seed(rng.next_u64()), capacity)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<I, T, E, A: Allocator> ScrambleProducer<I, T, E, A> {
+    /// Like `new`, but allocates the buffer with the given allocator.
+    pub fn new_in(inner: I, operations: ProduceOperations, capacity: NonZeroUsize, alloc: A) -> Self {
+        ScrambleProducer {
+            inner,
+            buf: FixedBuffer::new_in(capacity, alloc),
+            err: None,
+            operations: operations.0,
+            operations_index: 0,
+        }
+    }
+
+    /// Like `from_seed`, but allocates the buffer with the given allocator. The derived
+    /// operations are still allocated with the global allocator, just like those passed to
+    /// `new_in`.
+    pub fn from_seed_in(inner: I, seed: u64, alloc: A) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let capacity = NonZeroUsize::new(1 + rng.below(64)).unwrap();
+        Self::new_in(inner, ProduceOperations::from_seed(rng.next_u64()), capacity, alloc)
+    }
 }
 
-impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E> Producer for ScrambleProducer<I, T, E> {
+impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E, A: Allocator> Producer for ScrambleProducer<I, T, E, A> {
     type Item = T;
     type Error = E;
 
@@ -110,9 +172,19 @@ impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E> Producer for Scra
         }
         self.inner.slurp()
     }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.buf.get_amount();
+        if self.err.is_some() {
+            return (buffered, Some(buffered));
+        }
+
+        let (lower, upper) = self.inner.remaining_hint();
+        (lower.saturating_add(buffered), upper.and_then(|upper| upper.checked_add(buffered)))
+    }
 }
 
-impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E> BulkProducer for ScrambleProducer<I, T, E> {
+impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E, A: Allocator> BulkProducer for ScrambleProducer<I, T, E, A> {
     fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
         if self.buf.get_amount() == 0 && self.err.is_some() {
             return Err(self.err.take().unwrap());
@@ -133,7 +205,7 @@ impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E> BulkProducer for
     }
 }
 
-impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E> ScrambleProducer<I, T, E> {
+impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E, A: Allocator> ScrambleProducer<I, T, E, A> {
     fn perform_operation(&mut self) -> Result<(), E> {
         debug_assert!(self.buf.get_amount() < self.buf.get_capacity().get());
 
@@ -163,19 +235,19 @@ impl<I: BulkProducer<Item = T, Error = E>, T: Copy + Debug, E> ScrambleProducer<
     }
 }
 
-impl<I, T, E> Wrapper<I> for ScrambleProducer<I, T, E> {
+impl<I, T, E, A: Allocator> Wrapper<I> for ScrambleProducer<I, T, E, A> {
     fn into_inner(self) -> I {
         self.inner
     }
 }
 
-impl<I, T, E> AsRef<I> for ScrambleProducer<I, T, E> {
+impl<I, T, E, A: Allocator> AsRef<I> for ScrambleProducer<I, T, E, A> {
     fn as_ref(&self) -> &I {
         &self.inner
     }
 }
 
-impl<I, T, E> AsMut<I> for ScrambleProducer<I, T, E> {
+impl<I, T, E, A: Allocator> AsMut<I> for ScrambleProducer<I, T, E, A> {
     fn as_mut(&mut self) -> &mut I {
         &mut self.inner
     }
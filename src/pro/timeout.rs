@@ -0,0 +1,103 @@
+use core::convert::{AsRef, AsMut};
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+use wrapper::Wrapper;
+
+use crate::{Producer, BulkProducer};
+use crate::time::{Clock, TimeoutError};
+
+/// Wraps a producer such that `produce`, `producer_slots` and `slurp` fail with
+/// `TimeoutError::TimedOut` once the given deadline has passed, according to the given clock.
+///
+/// The deadline is only checked before delegating to the wrapped producer, an operation of the
+/// wrapped producer that blocks is not interrupted.
+pub fn with_timeout<P, K: Clock>(inner: P, clock: K, deadline: K::Instant) -> WithTimeout<P, K> {
+    WithTimeout { inner, clock, deadline }
+}
+
+/// A producer whose operations fail with `TimeoutError::TimedOut` once a deadline has passed.
+pub struct WithTimeout<P, K: Clock> {
+    inner: P,
+    clock: K,
+    deadline: K::Instant,
+}
+
+impl<P, K: Clock> WithTimeout<P, K> {
+    /// Returns the instant from which on the producer times out.
+    pub fn deadline(&self) -> K::Instant {
+        self.deadline
+    }
+
+    /// Sets the instant from which on the producer times out. The clock is checked against it
+    /// before delegating to the wrapped producer.
+    pub fn set_deadline(&mut self, deadline: K::Instant) {
+        self.deadline = deadline;
+    }
+
+    fn check<E>(&self) -> Result<(), TimeoutError<E>> {
+        if self.clock.now() >= self.deadline {
+            Err(TimeoutError::TimedOut)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<P, K: Clock> Wrapper<P> for WithTimeout<P, K> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P, K: Clock> AsRef<P> for WithTimeout<P, K> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, K: Clock> AsMut<P> for WithTimeout<P, K> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: Producer, K: Clock> Producer for WithTimeout<P, K> {
+    type Item = P::Item;
+    type Error = TimeoutError<P::Error>;
+
+    fn produce(&mut self) -> Result<Self::Item, Self::Error> {
+        self.check()?;
+        self.inner.produce().map_err(TimeoutError::Inner)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.check()?;
+        self.inner.slurp().map_err(TimeoutError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        // The deadline may pass at any time.
+        (0, self.inner.remaining_hint().1)
+    }
+}
+
+impl<P, K> BulkProducer for WithTimeout<P, K> where
+    P: BulkProducer,
+    P::Item: Copy,
+    K: Clock,
+{
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        self.check()?;
+        self.inner.producer_slots().map_err(TimeoutError::Inner)
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        self.inner.did_produce(amount)
+    }
+
+    fn producer_slots_vectored(&mut self) -> Result<(&Slice1<Self::Item>, &[Self::Item]), Self::Error> {
+        self.check()?;
+        self.inner.producer_slots_vectored().map_err(TimeoutError::Inner)
+    }
+}
@@ -1,18 +1,17 @@
 extern crate maybe_std as base;
 
-use base::alloc::{Allocator, Global};
-use base::boxed::Box;
 use base::num::NonZeroUsize;
 use base::mem::MaybeUninit;
 
 use slice_n::Slice1;
 
 use crate::*;
+use crate::allocator::*;
 
 /// A buffer holding up to a certain number of items.
 #[derive(Debug)]
 pub(crate) struct FixedBuffer<T, A = Global> where A: Allocator {
-    data: Box<[MaybeUninit<T>], A>,
+    data: BoxIn<[MaybeUninit<T>], A>,
     // reading resumes from this position
     read: usize,
     // amount of valid data
@@ -22,7 +21,7 @@ pub(crate) struct FixedBuffer<T, A = Global> where A: Allocator {
 impl<T> FixedBuffer<T> {
     pub fn new(capacity: NonZeroUsize) -> Self {
         FixedBuffer {
-            data: Box::new_uninit_slice(capacity.get()),
+            data: new_uninit_slice(capacity.get()),
             read: 0,
             amount: 0,
         }
@@ -30,9 +29,10 @@ impl<T> FixedBuffer<T> {
 }
 
 impl<T, A: Allocator> FixedBuffer<T, A> {
+    #[cfg(any(feature = "unstable", feature = "std"))]
     pub fn new_in(capacity: NonZeroUsize, alloc: A) -> Self {
         FixedBuffer {
-            data: Box::new_uninit_slice_in(capacity.get(), alloc),
+            data: new_uninit_slice_in(capacity.get(), alloc),
             read: 0,
             amount: 0,
         }
@@ -45,6 +45,13 @@ impl<T, A: Allocator> FixedBuffer<T, A> {
     pub fn get_capacity(&self) -> NonZeroUsize {
         unsafe { NonZeroUsize::new_unchecked(self.data.len()) }
     }
+
+    /// Forgets all buffered items.
+    #[cfg(feature = "std")]
+    pub fn clear(&mut self) {
+        self.read = 0;
+        self.amount = 0;
+    }
 }
 
 impl<T: Copy, A: Allocator> FixedBuffer<T, A> {
@@ -69,6 +76,24 @@ impl<T: Copy, A: Allocator> FixedBuffer<T, A> {
         }
     }
 
+    fn available_both(&mut self) -> (&mut [MaybeUninit<T>], &mut [MaybeUninit<T>]) {
+        let cap = self.capacity();
+        if self.is_data_contiguous() {
+            let (snd, fst) = self.data.split_at_mut(self.read);
+            return (&mut fst[self.amount..], snd);
+        } else {
+            return (&mut self.data[(self.read + self.amount) % cap..self.read], &mut []);
+        }
+    }
+
+    fn readable_both(&self) -> (&[MaybeUninit<T>], &[MaybeUninit<T>]) {
+        if self.is_data_contiguous() {
+            return (&self.data[self.read..self.write_to()], &[]);
+        } else {
+            return (&self.data[self.read..], &self.data[..self.write_to()]);
+        }
+    }
+
     fn capacity(&self) -> usize {
         self.data.len()
     }
@@ -88,7 +113,8 @@ impl<T: Copy, A: Allocator> Consumer for FixedBuffer<T, A> {
             return Err(());
         }
 
-        self.data[self.write_to()].write(item);
+        let write_to = self.write_to();
+        self.data[write_to].write(item);
         self.amount += 1;
         return Ok(());
     }
@@ -96,6 +122,11 @@ impl<T: Copy, A: Allocator> Consumer for FixedBuffer<T, A> {
     fn flush(&mut self) -> Result<(), Self::Error> {
         Ok(())
     }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        let free = self.capacity() - self.amount;
+        (free, Some(free))
+    }
 }
 
 impl<T: Copy, A: Allocator> BulkConsumer for FixedBuffer<T, A> {
@@ -110,6 +141,15 @@ impl<T: Copy, A: Allocator> BulkConsumer for FixedBuffer<T, A> {
     unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
         self.amount += amount.get();
     }
+
+    fn consumer_slots_vectored(&mut self) -> Result<(&mut Slice1<MaybeUninit<Self::Item>>, &mut [MaybeUninit<Self::Item>]), Self::Error> {
+        if self.amount >= self.capacity() {
+            return Err(());
+        }
+
+        let (fst, snd) = self.available_both();
+        Ok((unsafe { Slice1::from_slice_unchecked_mut(fst) }, snd))
+    }
 }
 
 impl<T: Copy, A: Allocator> Producer for FixedBuffer<T, A> {
@@ -131,6 +171,10 @@ impl<T: Copy, A: Allocator> Producer for FixedBuffer<T, A> {
     fn slurp(&mut self) -> Result<(), Self::Error> {
         Ok(())
     }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (self.amount, Some(self.amount))
+    }
 }
 
 impl<T: Copy, A: Allocator> BulkProducer for FixedBuffer<T, A> {
@@ -139,11 +183,25 @@ impl<T: Copy, A: Allocator> BulkProducer for FixedBuffer<T, A> {
             return Err(());
         }
 
-        Ok(unsafe { Slice1::from_slice_unchecked(MaybeUninit::slice_assume_init_ref(self.readable_fst())) })
+        Ok(unsafe { Slice1::from_slice_unchecked(slice_assume_init_ref(self.readable_fst())) })
     }
 
     fn did_produce(&mut self, amount: NonZeroUsize) {
         self.read = (self.read + amount.get()) % self.capacity();
         self.amount -= amount.get();
     }
+
+    fn producer_slots_vectored(&mut self) -> Result<(&Slice1<Self::Item>, &[Self::Item]), Self::Error> {
+        if self.amount == 0 {
+            return Err(());
+        }
+
+        let (fst, snd) = self.readable_both();
+        unsafe {
+            Ok((
+                Slice1::from_slice_unchecked(slice_assume_init_ref(fst)),
+                slice_assume_init_ref(snd),
+            ))
+        }
+    }
 }
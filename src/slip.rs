@@ -0,0 +1,335 @@
+//! Framing with the Serial Line Internet Protocol (SLIP), as specified in RFC 1055.
+//!
+//! SLIP terminates each frame with an `END` byte, and escapes `END` and `ESC` bytes within frames
+//! as `ESC ESC_END` and `ESC ESC_ESC` respectively. An `Encoder` is a `Consumer` of frames that
+//! writes their encodings into a wrapped `BulkConsumer`, a `Decoder` is a `Producer` of the frames
+//! whose encodings a wrapped `BulkProducer` produces.
+//!
+//! SLIP does not detect transmission errors beyond malformed escape sequences, so frames usually
+//! carry a checksum of the next protocol layer. The decoder writes each frame into a buffer of the
+//! caller, whose length bounds the length of the frames in addition to the `max_len` of the
+//! config.
+use core::cmp::min;
+use core::convert::{AsRef, AsMut};
+use core::marker::PhantomData;
+
+use wrapper::Wrapper;
+
+use crate::*;
+use crate::frame::{produce_frame, FrameState};
+
+/// Terminates a frame.
+pub const END: u8 = 0xc0;
+/// Starts an escape sequence.
+pub const ESC: u8 = 0xdb;
+/// Follows an `ESC` to encode an `END` byte within a frame.
+pub const ESC_END: u8 = 0xdc;
+/// Follows an `ESC` to encode an `ESC` byte within a frame.
+pub const ESC_ESC: u8 = 0xdd;
+
+/// Configures the framing of encoders and decoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    /// Whether an encoder writes an `END` byte before each frame as well, which flushes any line
+    /// noise that the receiver has read since the previous frame. Decoders always ignore empty
+    /// frames, so they accept frames with or without leading `END`.
+    pub leading_end: bool,
+    /// The maximal length of a frame, before encoding. Encoders reject longer frames, decoders
+    /// skip them.
+    pub max_len: usize,
+}
+
+impl Config {
+    /// Writes leading `END`s and allows frames of up to 1006 bytes, the limit of the original
+    /// Berkeley implementation.
+    pub const DEFAULT: Config = Config { leading_end: true, max_len: 1006 };
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::DEFAULT
+    }
+}
+
+fn is_special(byte: &u8) -> bool {
+    *byte == END || *byte == ESC
+}
+
+/// Creates an encoder that writes the SLIP encodings of all consumed frames into `inner`.
+pub fn encoder<C, F>(inner: C, config: Config) -> Encoder<C, F> {
+    Encoder {
+        inner,
+        config,
+        frames: PhantomData,
+    }
+}
+
+/// Consumes frames, i.e. anything that can be viewed as a slice of bytes, and writes their SLIP
+/// encodings into a wrapped `BulkConsumer`.
+pub struct Encoder<C, F> {
+    inner: C,
+    config: Config,
+    frames: PhantomData<fn(F)>,
+}
+
+impl<C, F> Wrapper<C> for Encoder<C, F> {
+    fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C, F> AsRef<C> for Encoder<C, F> {
+    fn as_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C, F> AsMut<C> for Encoder<C, F> {
+    fn as_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+/// Emitted by an `Encoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncodeError<E> {
+    /// The wrapped consumer emitted an error.
+    Inner(E),
+    /// The frame is longer than the maximal length of the config. Nothing has been written, the
+    /// encoder can continue with the next frame.
+    TooLong,
+}
+
+impl<C: BulkConsumer<Item = u8>, F: AsRef<[u8]>> Consumer for Encoder<C, F> {
+    type Item = F;
+    type Error = EncodeError<C::Error>;
+
+    fn consume(&mut self, item: F) -> Result<(), Self::Error> {
+        let mut frame = item.as_ref();
+        if frame.len() > self.config.max_len {
+            return Err(EncodeError::TooLong);
+        }
+
+        if self.config.leading_end {
+            self.inner.consume(END).map_err(EncodeError::Inner)?;
+        }
+
+        while !frame.is_empty() {
+            let run = frame.iter().position(is_special).unwrap_or(frame.len());
+            consume_slice(&mut self.inner, &frame[..run]).map_err(EncodeError::Inner)?;
+
+            if let Some(special) = frame.get(run) {
+                let escaped = if *special == END { ESC_END } else { ESC_ESC };
+                consume_slice(&mut self.inner, &[ESC, escaped]).map_err(EncodeError::Inner)?;
+                frame = &frame[run + 1..];
+            } else {
+                frame = &[];
+            }
+        }
+
+        self.inner.consume(END).map_err(EncodeError::Inner)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().map_err(EncodeError::Inner)
+    }
+}
+
+/// Emitted by a `Decoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError<E> {
+    /// The wrapped producer emitted an error, e.g. because the input has ended. All frames of
+    /// the input have been produced before.
+    Inner(E),
+    /// An `ESC` byte was followed by the given byte rather than by `ESC_END` or `ESC_ESC`. The
+    /// decoder skips the rest of the frame and continues with the next one.
+    InvalidEscape(u8),
+    /// A decoded frame was longer than the maximal length of the config or than the buffer of
+    /// the decoder. The decoder skips the rest of the frame and continues with the next one.
+    TooLong,
+    /// The input ended within a frame.
+    Incomplete,
+}
+
+// The progress of decoding a single frame.
+struct DecodeState {
+    // the maximal length of a frame, respecting both the config and the buffer
+    max_len: usize,
+    // the number of decoded bytes in the buffer
+    len: usize,
+    // whether any byte of the current frame has been read
+    in_frame: bool,
+    // whether the previous byte was an `ESC`
+    escaped: bool,
+    // whether the rest of the current frame is skipped, up to the next `END`
+    skip: bool,
+}
+
+impl DecodeState {
+    fn reset(&mut self) {
+        self.len = 0;
+        self.in_frame = false;
+        self.escaped = false;
+    }
+
+    // Discards the current frame and skips its remaining bytes.
+    fn fail<E>(&mut self, e: DecodeError<E>) -> Option<Result<(), DecodeError<E>>> {
+        self.reset();
+        self.skip = true;
+        Some(Err(e))
+    }
+}
+
+impl<E> FrameState<E> for DecodeState {
+    type Error = DecodeError<E>;
+
+    fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn decode(&mut self, buffer: &mut [u8], bytes: &[u8]) -> (usize, Option<Result<(), DecodeError<E>>>) {
+        let mut read = 0;
+        while read < bytes.len() {
+            let byte = bytes[read];
+
+            if self.skip {
+                read += 1;
+                if byte == END {
+                    self.skip = false;
+                }
+            } else if self.escaped {
+                read += 1;
+                let unescaped = match byte {
+                    ESC_END => END,
+                    ESC_ESC => ESC,
+                    // An `END` is not skipped, it ends the invalid frame.
+                    END => {
+                        self.reset();
+                        return (read, Some(Err(DecodeError::InvalidEscape(byte))));
+                    }
+                    _ => return (read, self.fail(DecodeError::InvalidEscape(byte))),
+                };
+
+                if self.len == self.max_len {
+                    return (read, self.fail(DecodeError::TooLong));
+                }
+                buffer[self.len] = unescaped;
+                self.len += 1;
+                self.escaped = false;
+            } else if byte == END {
+                read += 1;
+                if self.in_frame {
+                    self.in_frame = false;
+                    return (read, Some(Ok(())));
+                }
+                // Empty frames are ignored, they result from leading `END`s.
+            } else if byte == ESC {
+                read += 1;
+                self.in_frame = true;
+                self.escaped = true;
+            } else {
+                // Copy all bytes up to the next special one.
+                let available = &bytes[read..];
+                let run = available.iter().position(is_special).unwrap_or(available.len());
+                let amount = min(run, self.max_len - self.len);
+                buffer[self.len..self.len + amount].copy_from_slice(&available[..amount]);
+                self.len += amount;
+                self.in_frame = true;
+                read += amount;
+
+                if amount < run {
+                    return (read, self.fail(DecodeError::TooLong));
+                }
+            }
+        }
+        (read, None)
+    }
+
+    fn end(&mut self, e: E) -> DecodeError<E> {
+        if self.in_frame {
+            self.reset();
+            DecodeError::Incomplete
+        } else {
+            DecodeError::Inner(e)
+        }
+    }
+}
+
+/// Creates a decoder that produces the frames whose SLIP encodings `inner` produces, decoding
+/// each of them into `buffer`.
+pub fn decoder<P, B: AsMut<[u8]>>(inner: P, mut buffer: B, config: Config) -> Decoder<P, B> {
+    let max_len = min(config.max_len, buffer.as_mut().len());
+    Decoder {
+        inner,
+        buffer,
+        state: DecodeState {
+            max_len,
+            len: 0,
+            in_frame: false,
+            escaped: false,
+            skip: false,
+        },
+    }
+}
+
+/// Produces the frames encoded by the SLIP encodings of a wrapped `BulkProducer`.
+///
+/// Produces the length of each frame, whose unescaped bytes `frame` returns until the next call to
+/// `produce`. Consecutive `END`s, as written by encoders with `leading_end`, delimit empty
+/// frames, which are not produced.
+pub struct Decoder<P, B> {
+    inner: P,
+    buffer: B,
+    state: DecodeState,
+}
+
+impl<P, B: AsRef<[u8]>> Decoder<P, B> {
+    /// Returns the most recently produced frame.
+    pub fn frame(&self) -> &[u8] {
+        &self.buffer.as_ref()[..self.state.len]
+    }
+}
+
+impl<P, B> Wrapper<P> for Decoder<P, B> {
+    fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P, B> AsRef<P> for Decoder<P, B> {
+    fn as_ref(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, B> AsMut<P> for Decoder<P, B> {
+    fn as_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: BulkProducer<Item = u8>, B: AsMut<[u8]>> Producer for Decoder<P, B> {
+    /// The length of the produced frame.
+    type Item = usize;
+    type Error = DecodeError<P::Error>;
+
+    fn produce(&mut self) -> Result<usize, Self::Error> {
+        produce_frame(&mut self.inner, &mut self.state, self.buffer.as_mut())
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        self.inner.slurp().map_err(DecodeError::Inner)
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.remaining_hint().1)
+    }
+}
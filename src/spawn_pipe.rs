@@ -0,0 +1,153 @@
+extern crate maybe_std as base;
+
+use base::num::NonZeroUsize;
+use base::panic::resume_unwind;
+use base::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use base::thread::{self, JoinHandle};
+use base::vec::Vec;
+use core::cmp::min;
+
+use slice_n::Slice1;
+
+use crate::*;
+
+/// Moves all items from the `BulkProducer` into the `BulkConsumer` on two background threads:
+/// one fills a buffer of `buffer_size` items from the producer while the other drains the
+/// previously filled buffer into the consumer. This overlaps e.g. slow reads with CPU-bound
+/// processing of the data that has been read already.
+///
+/// Once the producer has emitted an error and all its items have been consumed, the consumer is
+/// flushed. If the consumer emits an error instead, the producer is stopped. Does not slurp.
+pub fn spawn_pipe<P, C, T>(producer: P, consumer: C, buffer_size: NonZeroUsize) -> PipeHandle<P::Error, C::Error> where
+    T: Copy + Send + 'static,
+    P: BulkProducer<Item = T> + Send + 'static,
+    P::Error: Send + 'static,
+    C: BulkConsumer<Item = T> + Send + 'static,
+    C::Error: Send + 'static,
+{
+    let ((empty_rx, full_tx), (full_rx, empty_tx)) = buffers(buffer_size);
+    PipeHandle {
+        producer: thread::spawn(move || fill(producer, buffer_size.get(), empty_rx, full_tx)),
+        consumer: thread::spawn(move || {
+            let mut consumer = consumer;
+            drain(&mut consumer, full_rx, empty_tx)
+        }),
+    }
+}
+
+/// Like `spawn_pipe`, but only the producer is moved to a background thread, the calling thread
+/// drains the filled buffers into the `BulkConsumer` itself. The consumer therefore need not be
+/// `Send`. Blocks until both sides have finished, and returns the errors of both sides. If the
+/// background thread has panicked, the panic is resumed on the current thread.
+pub fn pipe_from_thread<P, C, T>(producer: P, consumer: &mut C, buffer_size: NonZeroUsize) -> PipeErrors<P::Error, C::Error> where
+    T: Copy + Send + 'static,
+    P: BulkProducer<Item = T> + Send + 'static,
+    P::Error: Send + 'static,
+    C: BulkConsumer<Item = T>,
+{
+    let ((empty_rx, full_tx), (full_rx, empty_tx)) = buffers(buffer_size);
+    let producer = thread::spawn(move || fill(producer, buffer_size.get(), empty_rx, full_tx));
+    // Dropping the channel ends of the draining side stops the producer thread if the consumer
+    // has emitted an error.
+    let consumer = drain(consumer, full_rx, empty_tx);
+    PipeErrors {
+        producer: producer.join().unwrap_or_else(|payload| resume_unwind(payload)),
+        consumer,
+    }
+}
+
+// The channel ends of one side of a pipe: buffers are received from one and sent to the other.
+type Ends<T> = (Receiver<Vec<T>>, SyncSender<Vec<T>>);
+
+// Creates the channels through which the two sides exchange two buffers, with both buffers
+// waiting to be filled. Returns the ends of the filling side and those of the draining side.
+fn buffers<T>(buffer_size: NonZeroUsize) -> (Ends<T>, Ends<T>) {
+    let (full_tx, full_rx) = sync_channel(2);
+    let (empty_tx, empty_rx) = sync_channel(2);
+    for _ in 0..2 {
+        let _ = empty_tx.send(Vec::with_capacity(buffer_size.get()));
+    }
+    ((empty_rx, full_tx), (full_rx, empty_tx))
+}
+
+// Fills the empty buffers with items from the producer and passes them on. Returns `None` if the
+// draining side has stopped before the producer emitted an error.
+fn fill<P, T>(mut p: P, buffer_size: usize, empty: Receiver<Vec<T>>, full: SyncSender<Vec<T>>) -> Option<P::Error> where
+    T: Copy,
+    P: BulkProducer<Item = T>,
+{
+    loop {
+        let mut buf = empty.recv().ok()?;
+        buf.clear();
+
+        while buf.len() < buffer_size {
+            match p.producer_slots() {
+                Ok(s) => {
+                    let amount = min(s.len_(), buffer_size - buf.len());
+                    buf.extend_from_slice(&s[..amount]);
+                    p.did_produce(unsafe { NonZeroUsize::new_unchecked(amount) });
+                }
+                Err(e) => {
+                    if !buf.is_empty() {
+                        let _ = full.send(buf);
+                    }
+                    return Some(e);
+                }
+            }
+        }
+
+        full.send(buf).ok()?;
+    }
+}
+
+// Drains the full buffers into the consumer and hands them back. Flushes the consumer once the
+// filling side is done.
+fn drain<C, T>(c: &mut C, full: Receiver<Vec<T>>, empty: SyncSender<Vec<T>>) -> Option<C::Error> where
+    T: Copy,
+    C: BulkConsumer<Item = T>,
+{
+    while let Ok(buf) = full.recv() {
+        let mut position = 0;
+        while let Some(s) = Slice1::from_slice(&buf[position..]) {
+            match c.bulk_consume(s) {
+                Ok(amount) => position += amount.get(),
+                Err(e) => return Some(e),
+            }
+        }
+
+        let _ = empty.send(buf);
+    }
+
+    c.flush().err()
+}
+
+/// The errors with which the two sides of a `spawn_pipe` have finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipeErrors<PE, CE> {
+    /// The error emitted by the producer, or `None` if the consumer emitted an error before the
+    /// producer did.
+    pub producer: Option<PE>,
+    /// The error emitted by the consumer, including while flushing, or `None` if it consumed all
+    /// items and flushed successfully.
+    pub consumer: Option<CE>,
+}
+
+/// A handle to the threads of a `spawn_pipe`.
+#[derive(Debug)]
+pub struct PipeHandle<PE, CE> {
+    producer: JoinHandle<Option<PE>>,
+    consumer: JoinHandle<Option<CE>>,
+}
+
+impl<PE, CE> PipeHandle<PE, CE> {
+    /// Waits for both threads to finish and returns the errors of both sides. If either thread
+    /// has panicked, the panic is resumed on the current thread.
+    pub fn join(self) -> PipeErrors<PE, CE> {
+        let producer = self.producer.join();
+        let consumer = self.consumer.join();
+        PipeErrors {
+            producer: producer.unwrap_or_else(|payload| resume_unwind(payload)),
+            consumer: consumer.unwrap_or_else(|payload| resume_unwind(payload)),
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! A single-producer, single-consumer ring buffer whose two halves can be used from different
+//! execution contexts, e.g. an interrupt handler and an async task.
+//!
+//! The buffer is backed by an array and needs neither the standard library nor an allocator.
+//! Both halves are nonblocking, they emit an error if there is currently no data or no space
+//! respectively. Async code can instead use the `poll_*` methods, which register the waker of the
+//! current task so that it gets woken once the other half has made progress.
+//!
+//! Since `SpscBuffer::new` is a `const fn`, a buffer can be placed in a `static`, e.g. to connect
+//! an interrupt handler to the main loop of some firmware. `SpscBuffer::try_split` then hands out
+//! the two halves exactly once.
+//!
+//! The halves themselves only need atomic loads and stores. The `poll_*` methods and `try_split`
+//! additionally need atomic compare-and-swap operations, so they are only available on targets
+//! that support them, i.e. with `target_has_atomic = "ptr"`. On other targets, e.g. `thumbv6m`
+//! microcontrollers, the halves are only available via `split`.
+use core::cell::UnsafeCell;
+use core::cmp::min;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+use core::slice;
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_has_atomic = "ptr")]
+use core::task::{Context, Poll, Waker};
+
+use slice_n::Slice1;
+
+use crate::*;
+
+/// A ring buffer holding up to `N` items, which can be split into a `SpscConsumer` that writes
+/// into the buffer and a `SpscProducer` that reads from it.
+pub struct SpscBuffer<T, const N: usize> {
+    data: UnsafeCell<[MaybeUninit<T>; N]>,
+    // Both indices count modulo `2 * N`, so that a full buffer can be distinguished from an empty
+    // one. Only the `SpscProducer` modifies `read`, only the `SpscConsumer` modifies `write`.
+    read: AtomicUsize,
+    write: AtomicUsize,
+    // registered by the producer half when it finds no data
+    #[cfg(target_has_atomic = "ptr")]
+    data_waker: AtomicWaker,
+    // registered by the consumer half when it finds no space
+    #[cfg(target_has_atomic = "ptr")]
+    space_waker: AtomicWaker,
+    // whether `try_split` has handed out the halves already
+    #[cfg(target_has_atomic = "ptr")]
+    split: AtomicBool,
+}
+
+unsafe impl<T: Send, const N: usize> Sync for SpscBuffer<T, N> {}
+
+impl<T, const N: usize> SpscBuffer<T, N> {
+    /// Creates a new, empty buffer. Panics if `N` is zero.
+    pub const fn new() -> Self {
+        assert!(N > 0, "an SpscBuffer needs a nonzero capacity");
+        SpscBuffer {
+            // An array of `MaybeUninit`s does not need to be initialized.
+            data: UnsafeCell::new(unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() }),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+            #[cfg(target_has_atomic = "ptr")]
+            data_waker: AtomicWaker::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            space_waker: AtomicWaker::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Splits the buffer into a consumer that writes into it and a producer that reads from it.
+    /// The two halves can be sent to different threads or interrupt contexts.
+    pub fn split(&mut self) -> (SpscConsumer<'_, T, N>, SpscProducer<'_, T, N>) {
+        (SpscConsumer(&*self), SpscProducer(&*self))
+    }
+
+    /// Splits a shared buffer, e.g. one in a `static`, into a consumer that writes into it and a
+    /// producer that reads from it. Returns `None` if the buffer has been split via this method
+    /// before, so that there is never more than one of each half.
+    #[cfg(target_has_atomic = "ptr")]
+    pub fn try_split(&self) -> Option<(SpscConsumer<'_, T, N>, SpscProducer<'_, T, N>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            Some((SpscConsumer(self), SpscProducer(self)))
+        }
+    }
+
+    // The number of items in the buffer, given the two indices.
+    fn amount(read: usize, write: usize) -> usize {
+        (write + 2 * N - read) % (2 * N)
+    }
+
+    fn slots(&self, start: usize, len: usize) -> *mut MaybeUninit<T> {
+        debug_assert!(start + len <= N);
+        unsafe { (self.data.get() as *mut MaybeUninit<T>).add(start) }
+    }
+}
+
+/// The half of a `SpscBuffer` that consumes items by writing them into the buffer.
+pub struct SpscConsumer<'a, T, const N: usize>(&'a SpscBuffer<T, N>);
+
+impl<'a, T: Copy, const N: usize> SpscConsumer<'a, T, N> {
+    /// Like `consumer_slots`, but if there is currently no space, registers the waker of the given
+    /// context to be woken once the `SpscProducer` has produced some items.
+    #[cfg(target_has_atomic = "ptr")]
+    pub fn poll_consumer_slots(&mut self, cx: &mut Context<'_>) -> Poll<&mut Slice1<MaybeUninit<T>>> {
+        if self.is_full() {
+            self.0.space_waker.register(cx.waker());
+            // The producer might have made progress before the waker was registered.
+            if self.is_full() {
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(self.consumer_slots().unwrap())
+    }
+
+    #[cfg(target_has_atomic = "ptr")]
+    fn is_full(&self) -> bool {
+        let read = self.0.read.load(Ordering::Acquire);
+        let write = self.0.write.load(Ordering::Relaxed);
+        SpscBuffer::<T, N>::amount(read, write) == N
+    }
+}
+
+impl<'a, T: Copy, const N: usize> Consumer for SpscConsumer<'a, T, N> {
+    type Item = T;
+    /// Emitted when there is currently no space for writing available.
+    type Error = ();
+
+    fn consume(&mut self, item: T) -> Result<(), Self::Error> {
+        self.consumer_slots()?[0] = MaybeUninit::new(item);
+        unsafe { self.did_consume(NonZeroUsize::new_unchecked(1)) };
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn capacity_hint(&self) -> (usize, Option<usize>) {
+        let read = self.0.read.load(Ordering::Acquire);
+        let write = self.0.write.load(Ordering::Relaxed);
+        (N - SpscBuffer::<T, N>::amount(read, write), None)
+    }
+}
+
+impl<'a, T: Copy, const N: usize> BulkConsumer for SpscConsumer<'a, T, N> {
+    fn consumer_slots(&mut self) -> Result<&mut Slice1<MaybeUninit<Self::Item>>, Self::Error> {
+        let read = self.0.read.load(Ordering::Acquire);
+        let write = self.0.write.load(Ordering::Relaxed);
+        let free = N - SpscBuffer::<T, N>::amount(read, write);
+        if free == 0 {
+            return Err(());
+        }
+
+        let start = write % N;
+        let len = min(free, N - start);
+        // The producer half does not access the free slots.
+        Ok(unsafe { Slice1::from_slice_unchecked_mut(slice::from_raw_parts_mut(self.0.slots(start, len), len)) })
+    }
+
+    unsafe fn did_consume(&mut self, amount: NonZeroUsize) {
+        let write = self.0.write.load(Ordering::Relaxed);
+        self.0.write.store((write + amount.get()) % (2 * N), Ordering::Release);
+        #[cfg(target_has_atomic = "ptr")]
+        self.0.data_waker.wake();
+    }
+}
+
+/// The half of a `SpscBuffer` that produces the items that have been written into the buffer.
+pub struct SpscProducer<'a, T, const N: usize>(&'a SpscBuffer<T, N>);
+
+impl<'a, T: Copy, const N: usize> SpscProducer<'a, T, N> {
+    /// Like `producer_slots`, but if there are currently no items, registers the waker of the
+    /// given context to be woken once the `SpscConsumer` has consumed some items.
+    #[cfg(target_has_atomic = "ptr")]
+    pub fn poll_producer_slots(&mut self, cx: &mut Context<'_>) -> Poll<&Slice1<T>> {
+        if self.is_empty() {
+            self.0.data_waker.register(cx.waker());
+            // The consumer might have made progress before the waker was registered.
+            if self.is_empty() {
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(self.producer_slots().unwrap())
+    }
+
+    #[cfg(target_has_atomic = "ptr")]
+    fn is_empty(&self) -> bool {
+        let read = self.0.read.load(Ordering::Relaxed);
+        let write = self.0.write.load(Ordering::Acquire);
+        read == write
+    }
+}
+
+impl<'a, T: Copy, const N: usize> Producer for SpscProducer<'a, T, N> {
+    type Item = T;
+    /// Emitted when there are currently no items available.
+    type Error = ();
+
+    fn produce(&mut self) -> Result<T, Self::Error> {
+        let item = self.producer_slots()?[0];
+        self.did_produce(unsafe { NonZeroUsize::new_unchecked(1) });
+        Ok(item)
+    }
+
+    fn slurp(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        let read = self.0.read.load(Ordering::Relaxed);
+        let write = self.0.write.load(Ordering::Acquire);
+        (SpscBuffer::<T, N>::amount(read, write), None)
+    }
+}
+
+impl<'a, T: Copy, const N: usize> BulkProducer for SpscProducer<'a, T, N> {
+    fn producer_slots(&mut self) -> Result<&Slice1<Self::Item>, Self::Error> {
+        let read = self.0.read.load(Ordering::Relaxed);
+        let write = self.0.write.load(Ordering::Acquire);
+        let amount = SpscBuffer::<T, N>::amount(read, write);
+        if amount == 0 {
+            return Err(());
+        }
+
+        let start = read % N;
+        let len = min(amount, N - start);
+        // The consumer half has initialized these slots and does not access them anymore.
+        Ok(unsafe { Slice1::from_slice_unchecked(slice_assume_init_ref(slice::from_raw_parts(self.0.slots(start, len), len))) })
+    }
+
+    fn did_produce(&mut self, amount: NonZeroUsize) {
+        let read = self.0.read.load(Ordering::Relaxed);
+        self.0.read.store((read + amount.get()) % (2 * N), Ordering::Release);
+        #[cfg(target_has_atomic = "ptr")]
+        self.0.space_waker.wake();
+    }
+}
+
+// The waker of a `Context` is registered in the `waker` cell only in the `REGISTERING` state, and
+// taken out for waking only in the `WAKING` state.
+#[cfg(target_has_atomic = "ptr")]
+const WAITING: usize = 0;
+#[cfg(target_has_atomic = "ptr")]
+const REGISTERING: usize = 1;
+#[cfg(target_has_atomic = "ptr")]
+const WAKING: usize = 2;
+
+// Holds at most one `Waker`, which can be registered and woken from different contexts. Only one
+// context may register at a time. Needs atomic compare-and-swap operations.
+#[cfg(target_has_atomic = "ptr")]
+struct AtomicWaker {
+    state: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl Send for AtomicWaker {}
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl Sync for AtomicWaker {}
+
+#[cfg(target_has_atomic = "ptr")]
+impl AtomicWaker {
+    const fn new() -> Self {
+        AtomicWaker {
+            state: AtomicUsize::new(WAITING),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        match self.state.compare_exchange(WAITING, REGISTERING, Ordering::Acquire, Ordering::Acquire) {
+            Ok(_) => {
+                let slot = unsafe { &mut *self.waker.get() };
+                if !slot.as_ref().map_or(false, |old| old.will_wake(waker)) {
+                    *slot = Some(waker.clone());
+                }
+
+                if self.state.compare_exchange(REGISTERING, WAITING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                    // A wake happened while registering, so deliver it now.
+                    let waker = slot.take();
+                    self.state.store(WAITING, Ordering::Release);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+            Err(_) => {
+                // Currently waking, so the new waker would miss the wake.
+                waker.wake_by_ref();
+            }
+        }
+    }
+
+    fn wake(&self) {
+        if self.state.fetch_or(WAKING, Ordering::AcqRel) == WAITING {
+            let waker = unsafe { (*self.waker.get()).take() };
+            self.state.fetch_and(!WAKING, Ordering::Release);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
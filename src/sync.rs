@@ -7,16 +7,6 @@ pub use traits::*;
 mod cursor;
 pub use cursor::*;
 
-#[cfg(feature = "alloc")]
-mod into_vec;
-#[cfg(feature = "alloc")]
-pub use into_vec::*;
-
-#[cfg(feature = "alloc")]
-mod repeat;
-#[cfg(feature = "alloc")]
-pub use repeat::*;
-
 mod map_err;
 pub use map_err::*;
 
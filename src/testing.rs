@@ -0,0 +1,257 @@
+//! Reusable checks for third-party implementations of `BulkProducer` and `BulkConsumer`.
+//!
+//! The checks drive an implementation through every schedule of up to three operations that can
+//! be expressed by `ProduceOperation` or `ConsumeOperation` respectively (repeating the schedule
+//! until the implementation is done), and compare the outcome against a simple reference model.
+//! Any contract violation results in a panic whose message contains the offending schedule.
+extern crate maybe_std as base;
+
+use base::vec::Vec;
+use core::cmp::min;
+use core::fmt::Debug;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+
+use slice_n::Slice1;
+
+use crate::pro::{BulkProducer, ProduceOperation};
+use crate::con::{BulkConsumer, ConsumeOperation};
+use crate::{write_slice, slice_assume_init_ref};
+
+// The maximum length of the schedules that are checked.
+const MAX_SCHEDULE_LEN: usize = 3;
+// The amounts used for the bulk operations.
+const AMOUNTS: [usize; 4] = [1, 2, 3, 1024];
+
+/// Checks that the `BulkProducer`s created by `make` produce exactly the `expected` items before
+/// emitting an error, regardless of which operations are used to obtain them, and that they honor
+/// the contracts of the `BulkProducer` methods.
+pub fn check_bulk_producer<P, T, F>(make: F, expected: &[T]) where
+    T: Copy + Debug + PartialEq,
+    P: BulkProducer<Item = T>,
+    F: Fn() -> P,
+{
+    let ops = produce_operations();
+    for_each_schedule(ops.len(), |schedule| {
+        if schedule.iter().all(|i| ops[*i] == ProduceOperation::Slurp) {
+            return;
+        }
+        let schedule: Vec<&ProduceOperation> = schedule.iter().map(|i| &ops[*i]).collect();
+        check_producer_schedule(make(), expected, &schedule);
+    });
+}
+
+/// Checks that the `BulkConsumer`s created by `make` consume the `input` items in order,
+/// regardless of which operations are used to feed them, and that they honor the contracts of the
+/// `BulkConsumer` methods. `consumed` must return the items that have reached the data sink of a
+/// `BulkConsumer`. After a successful flush, these must be exactly the items the `BulkConsumer`
+/// accepted; if the `BulkConsumer` emitted an error, they must be a prefix thereof.
+pub fn check_bulk_consumer<C, T, F, G>(make: F, input: &[T], consumed: G) where
+    T: Copy + Debug + PartialEq,
+    C: BulkConsumer<Item = T>,
+    F: Fn() -> C,
+    G: Fn(C) -> Vec<T>,
+{
+    let ops = consume_operations();
+    for_each_schedule(ops.len(), |schedule| {
+        if schedule.iter().all(|i| ops[*i] == ConsumeOperation::Flush) {
+            return;
+        }
+        let schedule: Vec<&ConsumeOperation> = schedule.iter().map(|i| &ops[*i]).collect();
+        check_consumer_schedule(make(), input, &consumed, &schedule);
+    });
+}
+
+fn produce_operations() -> Vec<ProduceOperation> {
+    let mut ops = Vec::new();
+    ops.push(ProduceOperation::Produce);
+    ops.push(ProduceOperation::Slurp);
+    for n in AMOUNTS.iter() {
+        let n = NonZeroUsize::new(*n).unwrap();
+        ops.push(ProduceOperation::ProducerSlots(n));
+        ops.push(ProduceOperation::BulkProduce(n));
+    }
+    ops
+}
+
+fn consume_operations() -> Vec<ConsumeOperation> {
+    let mut ops = Vec::new();
+    ops.push(ConsumeOperation::Consume);
+    ops.push(ConsumeOperation::Flush);
+    for n in AMOUNTS.iter() {
+        let n = NonZeroUsize::new(*n).unwrap();
+        ops.push(ConsumeOperation::ConsumerSlots(n));
+        ops.push(ConsumeOperation::BulkConsume(n));
+    }
+    ops
+}
+
+// Calls `f` with every sequence of indices into `0..count` of length `1..=MAX_SCHEDULE_LEN`.
+fn for_each_schedule<F: FnMut(&[usize])>(count: usize, mut f: F) {
+    for len in 1..=MAX_SCHEDULE_LEN {
+        let mut schedule = Vec::new();
+        schedule.resize(len, 0);
+        loop {
+            f(&schedule);
+
+            let mut i = 0;
+            while i < len && schedule[i] == count - 1 {
+                schedule[i] = 0;
+                i += 1;
+            }
+            if i == len {
+                break;
+            }
+            schedule[i] += 1;
+        }
+    }
+}
+
+fn check_producer_schedule<P, T>(mut p: P, expected: &[T], schedule: &[&ProduceOperation]) where
+    T: Copy + Debug + PartialEq,
+    P: BulkProducer<Item = T>,
+{
+    let mut produced = 0;
+    let mut buffer = Vec::new();
+
+    for op in schedule.iter().cycle() {
+        let remaining = &expected[produced..];
+        let (lower, upper) = p.remaining_hint();
+        assert!(
+            lower <= remaining.len() && upper.map_or(true, |upper| upper >= remaining.len()),
+            "remaining_hint {:?} does not include the {} remaining items, schedule {:?}",
+            (lower, upper), remaining.len(), schedule,
+        );
+
+        match op {
+            ProduceOperation::Produce => match p.produce() {
+                Ok(item) => {
+                    assert!(!remaining.is_empty(), "produced more items than expected, schedule {:?}", schedule);
+                    assert_eq!(item, remaining[0], "produced wrong item at position {}, schedule {:?}", produced, schedule);
+                    produced += 1;
+                }
+                Err(_) => break,
+            }
+            ProduceOperation::ProducerSlots(n) => match p.producer_slots() {
+                Ok(slots) => {
+                    assert!(slots.len_() > 0, "producer_slots returned an empty slice, schedule {:?}", schedule);
+                    assert!(
+                        slots.len_() <= remaining.len(),
+                        "producer_slots returned {} items, but only {} were expected, schedule {:?}",
+                        slots.len_(), remaining.len(), schedule,
+                    );
+                    let amount = min(slots.len_(), n.get());
+                    assert_eq!(&slots[..amount], &remaining[..amount], "producer_slots returned wrong items at position {}, schedule {:?}", produced, schedule);
+                    p.did_produce(NonZeroUsize::new(amount).unwrap());
+                    produced += amount;
+                }
+                Err(_) => break,
+            }
+            ProduceOperation::BulkProduce(n) => {
+                buffer.clear();
+                buffer.resize_with(n.get(), MaybeUninit::uninit);
+                let slots = unsafe { Slice1::from_slice_unchecked_mut(&mut buffer[..]) };
+                match p.bulk_produce(slots) {
+                    Ok(amount) => {
+                        let amount = amount.get();
+                        assert!(amount <= n.get(), "bulk_produce claimed to write {} items into a buffer of size {}, schedule {:?}", amount, n, schedule);
+                        assert!(
+                            amount <= remaining.len(),
+                            "bulk_produce produced {} items, but only {} were expected, schedule {:?}",
+                            amount, remaining.len(), schedule,
+                        );
+                        let items = unsafe { slice_assume_init_ref(&buffer[..amount]) };
+                        assert_eq!(items, &remaining[..amount], "bulk_produce produced wrong items at position {}, schedule {:?}", produced, schedule);
+                        produced += amount;
+                    }
+                    Err(_) => break,
+                }
+            }
+            ProduceOperation::Slurp => match p.slurp() {
+                Ok(()) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    assert_eq!(produced, expected.len(), "emitted an error after only {} of {} items, schedule {:?}", produced, expected.len(), schedule);
+}
+
+fn check_consumer_schedule<C, T, G>(mut c: C, input: &[T], consumed: &G, schedule: &[&ConsumeOperation]) where
+    T: Copy + Debug + PartialEq,
+    C: BulkConsumer<Item = T>,
+    G: Fn(C) -> Vec<T>,
+{
+    let mut accepted = 0;
+    let mut failed = false;
+
+    for op in schedule.iter().cycle() {
+        let remaining = &input[accepted..];
+        if remaining.is_empty() {
+            break;
+        }
+
+        match op {
+            ConsumeOperation::Consume => match c.consume(remaining[0]) {
+                Ok(()) => accepted += 1,
+                Err(_) => {
+                    failed = true;
+                    break;
+                }
+            }
+            ConsumeOperation::ConsumerSlots(n) => match c.consumer_slots() {
+                Ok(slots) => {
+                    assert!(slots.len_() > 0, "consumer_slots returned an empty slice, schedule {:?}", schedule);
+                    let amount = min(min(slots.len_(), n.get()), remaining.len());
+                    write_slice(&mut slots[..amount], &remaining[..amount]);
+                    unsafe { c.did_consume(NonZeroUsize::new(amount).unwrap()) };
+                    accepted += amount;
+                }
+                Err(_) => {
+                    failed = true;
+                    break;
+                }
+            }
+            ConsumeOperation::BulkConsume(n) => {
+                let amount = min(n.get(), remaining.len());
+                let data = unsafe { Slice1::from_slice_unchecked(&remaining[..amount]) };
+                match c.bulk_consume(data) {
+                    Ok(amount_consumed) => {
+                        assert!(
+                            amount_consumed.get() <= amount,
+                            "bulk_consume claimed to consume {} items from a buffer of size {}, schedule {:?}",
+                            amount_consumed, amount, schedule,
+                        );
+                        accepted += amount_consumed.get();
+                    }
+                    Err(_) => {
+                        failed = true;
+                        break;
+                    }
+                }
+            }
+            ConsumeOperation::Flush => match c.flush() {
+                Ok(()) => {}
+                Err(_) => {
+                    failed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    if !failed && c.flush().is_err() {
+        failed = true;
+    }
+
+    let received = consumed(c);
+    if failed {
+        assert!(
+            received.len() <= accepted && &received[..] == &input[..received.len()],
+            "data sink received {:?}, which is not a prefix of the {} accepted items, schedule {:?}",
+            received, accepted, schedule,
+        );
+    } else {
+        assert_eq!(&received[..], &input[..accepted], "data sink did not receive the accepted items after flushing, schedule {:?}", schedule);
+    }
+}
@@ -0,0 +1,72 @@
+//! Abstractions over time, for adapters that give up once a deadline has passed.
+use core::cell::Cell;
+
+/// A source of the current time.
+pub trait Clock {
+    /// A point in time. Later points in time compare greater than earlier ones.
+    type Instant: Ord + Copy;
+
+    /// Returns the current time.
+    fn now(&self) -> Self::Instant;
+}
+
+impl<'a, K: Clock + ?Sized> Clock for &'a K {
+    type Instant = K::Instant;
+
+    fn now(&self) -> Self::Instant {
+        (**self).now()
+    }
+}
+
+/// A `Clock` backed by `std::time::Instant`.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StdClock;
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    type Instant = std::time::Instant;
+
+    fn now(&self) -> Self::Instant {
+        std::time::Instant::now()
+    }
+}
+
+/// A `Clock` that only advances when told to, for testing time-dependent code deterministically.
+/// The time is an arbitrary `u64`, e.g. a number of milliseconds.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ManualClock(Cell<u64>);
+
+impl ManualClock {
+    /// Creates a clock that starts at the given time.
+    pub fn new(now: u64) -> Self {
+        ManualClock(Cell::new(now))
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, now: u64) {
+        self.0.set(now)
+    }
+
+    /// Advances the current time by the given amount, stopping at `u64::MAX`.
+    pub fn advance(&self, by: u64) {
+        self.0.set(self.0.get().saturating_add(by))
+    }
+}
+
+impl Clock for ManualClock {
+    type Instant = u64;
+
+    fn now(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+/// The error emitted by the timeout adapters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeoutError<E> {
+    /// The deadline passed before the operation was started.
+    TimedOut,
+    /// The wrapped value emitted an error.
+    Inner(E),
+}
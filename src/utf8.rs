@@ -0,0 +1,56 @@
+// Incremental UTF-8 validation, for text that arrives in arbitrary chunks.
+
+/// Validates UTF-8 one byte at a time, so that code points may be split across chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Utf8Validator {
+    // the number of continuation bytes that the current code point still needs
+    needed: u8,
+    // the range of the next continuation byte, narrower than usual for the first continuation byte
+    // after some leading bytes, to reject overlong encodings, surrogates and too large code points
+    lower: u8,
+    upper: u8,
+}
+
+impl Utf8Validator {
+    pub(crate) const fn new() -> Self {
+        Utf8Validator { needed: 0, lower: 0x80, upper: 0xbf }
+    }
+
+    /// Returns whether the bytes so far are a prefix of valid UTF-8 after adding the given byte.
+    pub(crate) fn push(&mut self, byte: u8) -> bool {
+        if self.needed == 0 {
+            let (needed, lower, upper) = match byte {
+                0x00..=0x7f => return true,
+                0xc2..=0xdf => (1, 0x80, 0xbf),
+                0xe0 => (2, 0xa0, 0xbf),
+                0xe1..=0xec | 0xee..=0xef => (2, 0x80, 0xbf),
+                0xed => (2, 0x80, 0x9f),
+                0xf0 => (3, 0x90, 0xbf),
+                0xf1..=0xf3 => (3, 0x80, 0xbf),
+                0xf4 => (3, 0x80, 0x8f),
+                _ => return false,
+            };
+            self.needed = needed;
+            self.lower = lower;
+            self.upper = upper;
+            true
+        } else if byte < self.lower || byte > self.upper {
+            false
+        } else {
+            self.needed -= 1;
+            self.lower = 0x80;
+            self.upper = 0xbf;
+            true
+        }
+    }
+
+    /// Returns whether the bytes so far are a prefix of valid UTF-8 after adding the given bytes.
+    pub(crate) fn push_slice(&mut self, bytes: &[u8]) -> bool {
+        bytes.iter().all(|byte| self.push(*byte))
+    }
+
+    /// Returns whether the bytes so far end at the end of a code point.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.needed == 0
+    }
+}
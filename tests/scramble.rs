@@ -0,0 +1,48 @@
+//! Runs the pipe functions under many seeded scrambles of both sides. A failing assertion names
+//! the seed with which to reproduce it.
+#![cfg(feature = "alloc")]
+
+use rw::*;
+use rw::con::{Consumer, IntoVec, ScrambleConsumer};
+use rw::pro::ScrambleProducer;
+use wrapper::Wrapper;
+
+const SEEDS: u64 = 2000;
+
+type Collect = ScrambleConsumer<con::MapErr<IntoVec<u16>, fn(Infallible)>, u16>;
+
+fn check<F>(pipe_fn: F) where
+    F: Fn(&mut ScrambleProducer<pro::Cursor<'_, u16>, u16, ()>, &mut Collect),
+{
+    let items: Vec<u16> = (0..512).collect();
+    for seed in 0..SEEDS {
+        let len = (seed % 512) as usize;
+        let mut p = ScrambleProducer::from_seed(pro::cursor(&items[..len]), seed);
+        let inner = con::map_err(IntoVec::new(), (|e| match e {}) as fn(Infallible));
+        let mut c: Collect = ScrambleConsumer::from_seed(inner, !seed);
+
+        pipe_fn(&mut p, &mut c);
+        assert_eq!(c.flush(), Ok(()), "failing seed: {}", seed);
+        assert_eq!(&c.into_inner().into_inner().into_vec()[..], &items[..len], "failing seed: {}", seed);
+    }
+}
+
+#[test]
+fn scrambled_pipe() {
+    check(|p, c| pipe(p, c));
+}
+
+#[test]
+fn scrambled_bulk_produce_all() {
+    check(|p, c| bulk_produce_all(p, c));
+}
+
+#[test]
+fn scrambled_bulk_consume_all() {
+    check(|p, c| bulk_consume_all(p, c));
+}
+
+#[test]
+fn scrambled_bulk_pipe() {
+    check(|p, c| bulk_pipe(p, c));
+}